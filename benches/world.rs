@@ -0,0 +1,209 @@
+//! World optimisation and nav mesh generation on synthetic terrain.
+//!
+//! Run with `cargo bench --bench world --features bench`, the feature exposes the stages of
+//! `build_nav_mesh` which are benchmarked on their own. A 12 chunk view distance loads 25 * 25
+//! chunk columns of 24 sub chunks each, so keeping up with a full reload of the view means
+//! building about 15000 sub chunks, and walking loads a new row of 25 columns (600 sub chunks)
+//! about every 3.7 seconds. Compare that against the sum of `sub_chunk_new` and `build_nav_mesh`.
+
+use bevy::math::IVec3;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use smallvec::smallvec;
+use wallace::aabb::{
+    aabb_3d::Aabb3D,
+    agent::AgentParams,
+    optimise_world::{SubChunk, SubChunkSource, CHUNK_WIDTH, SUB_CHUNK_HEIGHT},
+};
+
+const BOTTOM_SLAB: Aabb3D = Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0]);
+const CARPET: Aabb3D = Aabb3D([0.0, 0.0, 0.0, 1.0, 0.0625, 1.0]);
+const FENCE: Aabb3D = Aabb3D([0.0, 0.0, 0.375, 1.0, 1.5, 0.625]);
+const LANTERN: Aabb3D = Aabb3D([0.3125, 0.0, 0.3125, 0.6875, 0.4375, 0.6875]);
+
+/// Deterministic pseudo random value in `0..256` for a position
+fn hash(x: usize, y: usize, z: usize) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x9E37_79B1)
+        ^ (y as u32).wrapping_mul(0x85EB_CA77)
+        ^ (z as u32).wrapping_mul(0xC2B2_AE3D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h & 0xFF
+}
+
+fn ground(source: &mut SubChunkSource, height: impl Fn(usize, usize) -> usize) {
+    for (z, plane) in source.iter_mut().enumerate() {
+        for (x, column) in plane.iter_mut().enumerate() {
+            for block in column.iter_mut().take(height(x, z)) {
+                *block = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+    }
+}
+
+fn flat() -> SubChunkSource {
+    let mut source: SubChunkSource = Default::default();
+    ground(&mut source, |_, _| 4);
+    source
+}
+
+/// Solid stone with winding tunnels and some loose blocks on the tunnel floors
+fn caves() -> SubChunkSource {
+    let mut source: SubChunkSource = Default::default();
+    for (z, plane) in source.iter_mut().enumerate() {
+        for (x, column) in plane.iter_mut().enumerate() {
+            for (y, block) in column.iter_mut().enumerate() {
+                let (fx, fy, fz) = (x as f32, y as f32, z as f32);
+                let tunnel = (fx * 0.45).sin() + (fz * 0.35).cos() + (fy * 0.6 + fx * 0.2).sin();
+                if tunnel < 1.1 {
+                    *block = smallvec![Aabb3D::FULL_BLOCK];
+                } else if hash(x, y, z) < 24 {
+                    *block = smallvec![BOTTOM_SLAB];
+                }
+            }
+        }
+    }
+    source
+}
+
+/// Rolling ground with trees
+fn forest() -> SubChunkSource {
+    let mut source: SubChunkSource = Default::default();
+    let height =
+        |x: usize, z: usize| 3 + ((x as f32 * 0.4).sin() + (z as f32 * 0.3).cos() + 2.0) as usize;
+    ground(&mut source, height);
+
+    for z in (2..CHUNK_WIDTH - 2).step_by(4) {
+        for x in (2..CHUNK_WIDTH - 2).step_by(4) {
+            let (x, z) = (
+                x + hash(x, 0, z) as usize % 2,
+                z + hash(z, 0, x) as usize % 2,
+            );
+            let base = height(x, z);
+            let top = (base + 5).min(SUB_CHUNK_HEIGHT);
+            for y in base..top {
+                source[z][x][y] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+            // Leaves around the top of the trunk
+            for y in top.saturating_sub(2)..top {
+                for lz in z - 1..=z + 1 {
+                    for lx in x - 1..=x + 1 {
+                        if source[lz][lx][y].is_empty() && hash(lx, y, lz) > 40 {
+                            source[lz][lx][y] = smallvec![Aabb3D::FULL_BLOCK];
+                        }
+                    }
+                }
+            }
+        }
+    }
+    source
+}
+
+/// Flat ground with small houses, fenced paths, lanterns and carpets
+fn village() -> SubChunkSource {
+    let mut source = flat();
+    let floor = 4;
+
+    for (house_x, house_z) in [(1, 1), (9, 1), (1, 9), (9, 9)] {
+        for z in house_z..house_z + 6 {
+            for x in house_x..house_x + 6 {
+                let wall = x == house_x || x == house_x + 5 || z == house_z || z == house_z + 5;
+                let doorway = z == house_z && x == house_x + 2;
+                for y in floor..floor + 3 {
+                    if wall && !(doorway && y < floor + 2) {
+                        source[z][x][y] = smallvec![Aabb3D::FULL_BLOCK];
+                    }
+                }
+                source[z][x][floor + 3] = smallvec![BOTTOM_SLAB];
+                if !wall && (x + z) % 2 == 0 {
+                    source[z][x][floor] = smallvec![CARPET];
+                }
+            }
+        }
+        source[house_z + 3][house_x + 3][floor] = smallvec![LANTERN];
+    }
+
+    // Fences along the path between the houses
+    for x in 0..CHUNK_WIDTH {
+        if x % 5 != 2 {
+            source[7][x][floor] = smallvec![FENCE];
+        }
+    }
+    source
+}
+
+fn scenes() -> [(&'static str, SubChunkSource); 4] {
+    [
+        ("flat", flat()),
+        ("caves", caves()),
+        ("forest", forest()),
+        ("village", village()),
+    ]
+}
+
+fn sub_chunk_new(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sub_chunk_new");
+    for (name, source) in scenes() {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || source.clone(),
+                |source| SubChunk::new(IVec3::ZERO, source),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn apply_greedy_meshing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_greedy_meshing");
+    for (name, source) in scenes() {
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || sub_chunk.clone(),
+                |sub_chunk| sub_chunk.apply_greedy_meshing(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn build_nav_mesh(c: &mut Criterion) {
+    let agent = AgentParams::PLAYER;
+    let mut group = c.benchmark_group("build_nav_mesh");
+    for (name, source) in scenes() {
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+        group.bench_function(name, |b| {
+            b.iter(|| sub_chunk.build_nav_mesh(black_box(&agent)))
+        });
+    }
+    group.finish();
+}
+
+fn cut_floor(c: &mut Criterion) {
+    let agent = AgentParams::PLAYER;
+    let mut group = c.benchmark_group("cut_floor");
+    for (name, source) in scenes() {
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+        let floor = sub_chunk.bench_floor_layers(&agent);
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || floor.clone(),
+                |floor| sub_chunk.bench_cut_floor(floor, black_box(&agent)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    sub_chunk_new,
+    apply_greedy_meshing,
+    build_nav_mesh,
+    cut_floor
+);
+criterion_main!(benches);
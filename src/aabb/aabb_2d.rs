@@ -1,4 +1,7 @@
 use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::predicates::{self, merge_all};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Point2D {
@@ -6,7 +9,7 @@ pub struct Point2D {
     pub y: f32,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Aabb2D {
     pub min_x: f32,
     pub min_y: f32,
@@ -51,9 +54,9 @@ impl Aabb2D {
         Aabb2CmpResult::None
     }
 
+    /// Rectangles share some area, touching edges or corners don't count
     pub fn overlaps(&self, other: &Self) -> bool {
-        return (self.min_x < other.max_x || other.min_x < self.max_x)
-            && (self.min_y < other.max_y || other.min_y < self.max_y);
+        predicates::overlaps(self, other, predicates::EPSILON)
     }
 
     pub fn cut(&self, other: &Self) -> Option<(Aabb2D, Aabb2D)> {
@@ -154,6 +157,59 @@ impl Aabb2D {
         return vec![a.clone(), b.clone()];
     }
 
+    /// Single rectangle covering both, if they share a whole edge
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        (0..2).find_map(|axis| self.merge_along(other, axis))
+    }
+
+    /// Single rectangle covering both, if they share a whole edge normal to `axis`
+    fn merge_along(&self, other: &Self, axis: usize) -> Option<Self> {
+        let a = self.to_array();
+        let b = other.to_array();
+        let edge_axis = 1 - axis;
+
+        let mergeable = (a[0][edge_axis] - b[0][edge_axis]).abs() <= predicates::EPSILON
+            && (a[1][edge_axis] - b[1][edge_axis]).abs() <= predicates::EPSILON
+            && predicates::interval_overlap(a[0][axis], a[1][axis], b[0][axis], b[1][axis])
+                >= -predicates::EPSILON;
+
+        if mergeable {
+            Some(Self {
+                min_x: self.min_x.min(other.min_x),
+                min_y: self.min_y.min(other.min_y),
+                max_x: self.max_x.max(other.max_x),
+                max_y: self.max_y.max(other.max_y),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Disjoint rectangles covering the same area as all of `aabbs`
+    pub fn union_all(aabbs: impl IntoIterator<Item = Self>) -> Vec<Self> {
+        let mut aabbs: Vec<Self> = aabbs.into_iter().flat_map(Self::validate).collect();
+        aabbs.sort_by(|a, b| b.area().total_cmp(&a.area()));
+
+        let mut result: Vec<Self> = vec![];
+        for aabb in aabbs {
+            let mut pieces = vec![aabb];
+            for other in result.iter() {
+                pieces = pieces
+                    .into_iter()
+                    .flat_map(|piece| piece.subtract(other))
+                    .collect();
+            }
+            result.extend(pieces);
+        }
+
+        // Merging one axis at a time joins rows, then whole areas
+        for axis in 0..2 {
+            merge_all(&mut result, |a, b| a.merge_along(b, axis));
+        }
+        merge_all(&mut result, Self::merge);
+        result
+    }
+
     pub fn validate(self) -> Option<Self> {
         if self.min_x < self.max_x && self.min_y < self.max_y {
             Some(self)
@@ -162,6 +218,13 @@ impl Aabb2D {
         }
     }
 
+    /// Whether the rectangle is thinner than `predicates::EPSILON` along either axis, such as the
+    /// float noise left over when subtracting nearly coincident rectangles
+    pub fn is_sliver(&self) -> bool {
+        self.max_x - self.min_x <= predicates::EPSILON
+            || self.max_y - self.min_y <= predicates::EPSILON
+    }
+
     pub fn subtract(&self, other: &Self) -> Vec<Aabb2D> {
         // [       ]
         // [ ] X [ ]
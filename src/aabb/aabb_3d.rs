@@ -1,11 +1,14 @@
 use azalea::core::aabb::AABB;
+use bevy::math::Vec3;
+use serde::{Deserialize, Serialize};
 
 use super::aabb_2d::Aabb2D;
+use super::predicates::{self, merge_all};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Point3D(pub [f32; 2]);
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Aabb3D(pub [f32; 6]);
 
 impl Aabb3D {
@@ -40,6 +43,11 @@ impl Aabb3D {
         true
     }
 
+    /// Boxes share some volume, touching faces, edges or corners don't count
+    pub fn overlaps(&self, other: &Self) -> bool {
+        predicates::overlaps(self, other, predicates::EPSILON)
+    }
+
     pub fn volume(&self) -> f32 {
         return (0..3).map(|axis| self.0[axis + 3] - self.0[axis]).product();
     }
@@ -48,11 +56,9 @@ impl Aabb3D {
         let deltas = [0, 1, 2, 3, 4, 5].map(|index| self.0[index] - other.0[index]);
 
         if deltas[0..3].iter().all(|v| v <= &0.0f32) && deltas[3..6].iter().all(|v| v >= &0.0f32) {
-            dbg!("a");
             return SupersetResult::A;
         }
         if deltas[0..3].iter().all(|v| v >= &0.0f32) && deltas[3..6].iter().all(|v| v <= &0.0f32) {
-            dbg!("b");
             return SupersetResult::B;
         }
         SupersetResult::None
@@ -125,49 +131,187 @@ impl Aabb3D {
             SupersetResult::None => {}
         };
 
-        // check for possible clean and reorder
-        // let (a, b) = if {
-        //     let a_array = self.0;
-
-        //     let count: usize = (0usize..4usize)
-        //         .map(|i| {
-        //             let x_index = i.rem_euclid(2);
-        //             let y_index = i.div_euclid(2);
-
-        //             let x = a_array[x_index][0];
-        //             let y = a_array[y_index][1];
-
-        //             other.strict_contains(&Point3D { x, y }) as usize
-        //         })
-        //         .sum();
-        //     count == 2
-        // } {
-        //     (other, self)
-        // } else {
-        //     (self, other)
-        // };
-        let (a, b) = (self, other);
-        dbg!((&a, &b));
-
-        // Try to cut b with edges of a
-        if let Some((b, c)) = a.cut(b) {
-            dbg!((&b, &c));
-
-            // Check if result rectangle before the cut is no longer needed
-            match a.superset(&b) {
-                SupersetResult::A => return vec![a.clone(), c],
-                SupersetResult::B => return vec![b, c],
-                SupersetResult::None => {}
-            };
-
-            // Since neither a/b was superset, cutting b again will produce one external piece, and one subset of a
-            if let Some((_, b)) = a.cut(&b) {
-                return vec![a.clone(), b, c];
+        // Overlapping boxes with a shared cross section form a single box
+        if self.overlaps(other) {
+            if let Some(merged) = self.merge(other) {
+                return vec![merged];
             }
         }
 
-        // Rectangles must have being disjoint
-        return vec![a.clone(), b.clone()];
+        // Cut pieces of other off with the faces of self until the rest is inside self, or
+        // doesn't overlap it. Each cut leaves a piece outside self, at most one per face.
+        let mut result = vec![self.clone()];
+        let mut rest = other.clone();
+        while let Some((before, after)) = self.cut(&rest) {
+            result.push(after);
+            rest = before;
+            if let SupersetResult::A = self.superset(&rest) {
+                return result;
+            }
+        }
+
+        // Boxes must have been disjoint
+        result.push(rest);
+        result
+    }
+
+    /// Single box covering both, if they share a whole face
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        (0..3).find_map(|axis| self.merge_along(other, axis))
+    }
+
+    /// Single box covering both, if they share a whole face normal to `axis`
+    fn merge_along(&self, other: &Self, axis: usize) -> Option<Self> {
+        let a = self.0;
+        let b = other.0;
+
+        let mergeable = (0..3)
+            .filter(|face_axis| *face_axis != axis)
+            .all(|face_axis| {
+                (a[face_axis] - b[face_axis]).abs() <= predicates::EPSILON
+                    && (a[face_axis + 3] - b[face_axis + 3]).abs() <= predicates::EPSILON
+            })
+            && predicates::interval_overlap(a[axis], a[axis + 3], b[axis], b[axis + 3])
+                >= -predicates::EPSILON;
+
+        if mergeable {
+            Some(Self([
+                a[0].min(b[0]),
+                a[1].min(b[1]),
+                a[2].min(b[2]),
+                a[3].max(b[3]),
+                a[4].max(b[4]),
+                a[5].max(b[5]),
+            ]))
+        } else {
+            None
+        }
+    }
+
+    pub fn validate(self) -> Option<Self> {
+        if (0..3).all(|axis| self.0[axis] < self.0[axis + 3]) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    /// Volume shared by both boxes
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Self([
+            self.0[0].max(other.0[0]),
+            self.0[1].max(other.0[1]),
+            self.0[2].max(other.0[2]),
+            self.0[3].min(other.0[3]),
+            self.0[4].min(other.0[4]),
+            self.0[5].min(other.0[5]),
+        ])
+        .validate()
+    }
+
+    /// Disjoint pieces of self not covered by `other`, at most six
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        // Full slabs below and above, then the z and x pieces are limited to the remaining
+        // range of the previous axes.
+        let [min_x, min_y, min_z, max_x, max_y, max_z] = self.0;
+        let inner_min_y = min_y.max(other.min_y());
+        let inner_max_y = max_y.min(other.max_y());
+        let inner_min_z = min_z.max(other.min_z());
+        let inner_max_z = max_z.min(other.max_z());
+
+        let below = Self([min_x, min_y, min_z, max_x, other.min_y().min(max_y), max_z]);
+        let above = Self([min_x, other.max_y().max(min_y), min_z, max_x, max_y, max_z]);
+        let front = Self([
+            min_x,
+            inner_min_y,
+            min_z,
+            max_x,
+            inner_max_y,
+            other.min_z().min(max_z),
+        ]);
+        let back = Self([
+            min_x,
+            inner_min_y,
+            other.max_z().max(min_z),
+            max_x,
+            inner_max_y,
+            max_z,
+        ]);
+        let left = Self([
+            min_x,
+            inner_min_y,
+            inner_min_z,
+            other.min_x().min(max_x),
+            inner_max_y,
+            inner_max_z,
+        ]);
+        let right = Self([
+            other.max_x().max(min_x),
+            inner_min_y,
+            inner_min_z,
+            max_x,
+            inner_max_y,
+            inner_max_z,
+        ]);
+
+        [below, above, front, back, left, right]
+            .into_iter()
+            .flat_map(Self::validate)
+            .collect()
+    }
+
+    pub fn clamp(&self, other: &Self) -> Self {
+        Self([0, 1, 2, 3, 4, 5].map(|index| {
+            let axis = index % 3;
+            self.0[index].clamp(other.0[axis], other.0[axis + 3])
+        }))
+    }
+
+    pub fn inflate(&self, amount: Vec3) -> Self {
+        Self([
+            self.0[0] - amount.x,
+            self.0[1] - amount.y,
+            self.0[2] - amount.z,
+            self.0[3] + amount.x,
+            self.0[4] + amount.y,
+            self.0[5] + amount.z,
+        ])
+    }
+
+    pub fn translate(&self, translation: Vec3) -> Self {
+        Self([
+            self.0[0] + translation.x,
+            self.0[1] + translation.y,
+            self.0[2] + translation.z,
+            self.0[3] + translation.x,
+            self.0[4] + translation.y,
+            self.0[5] + translation.z,
+        ])
+    }
+
+    /// Disjoint boxes covering the same volume as all of `aabbs`
+    pub fn union_all(aabbs: impl IntoIterator<Item = Self>) -> Vec<Self> {
+        let mut aabbs: Vec<Self> = aabbs.into_iter().flat_map(Self::validate).collect();
+        aabbs.sort_by(|a, b| b.volume().total_cmp(&a.volume()));
+
+        let mut result: Vec<Self> = vec![];
+        for aabb in aabbs {
+            let mut pieces = vec![aabb];
+            for other in result.iter() {
+                pieces = pieces
+                    .into_iter()
+                    .flat_map(|piece| piece.subtract(other))
+                    .collect();
+            }
+            result.extend(pieces);
+        }
+
+        // Merging one axis at a time joins rows, then planes, then volumes
+        for axis in 0..3 {
+            merge_all(&mut result, |a, b| a.merge_along(b, axis));
+        }
+        merge_all(&mut result, Self::merge);
+        result
     }
 
     pub fn surface_projection(&self, axis: usize) -> Aabb2D {
@@ -185,23 +329,38 @@ impl Aabb3D {
         }
     }
 
-    // pub fn cmp_faces(&self, other: &Self) -> [ContactType; 6] {
-    //     let mut result = [ContactType::None; 6];
-    //     for direction in 0..3 {
-    //         let self_surface = self.surface_projection(axis);
-    //         let other_surface = other.surface_projection(axis);
+    /// Contact between each face of self and the opposing face of `other`, indexed by `Axis`
+    pub fn cmp_faces(&self, other: &Self) -> [ContactType; 6] {
+        Axis::ALL.map(|direction| self.cmp_face(other, direction))
+    }
 
-    //         match self_surface.cmp(&other_surface) {
-    //             super::aabb_2d::Aabb2CmpResult::Superset => {
+    /// Contact between the face of self pointing in `direction` and the opposing face of `other`
+    ///
+    /// `Superset` means the face of self covers the face of `other`, identical faces are
+    /// `Superset`. Faces that only meet along an edge or at a corner are `None`.
+    pub fn cmp_face(&self, other: &Self, direction: Axis) -> ContactType {
+        let axis = direction.axis();
+        let (face, other_face) = if direction.is_positive() {
+            (self.0[axis + 3], other.0[axis])
+        } else {
+            (self.0[axis], other.0[axis + 3])
+        };
+        if (face - other_face).abs() > predicates::EPSILON {
+            return ContactType::None;
+        }
 
-    //             }
-    //             super::aabb_2d::Aabb2CmpResult::Subset => {}
-    //             super::aabb_2d::Aabb2CmpResult::Mixed => {}
-    //             super::aabb_2d::Aabb2CmpResult::None => {}
-    //         };
-    //     }
-    //     return result;
-    // }
+        let surface = self.surface_projection(axis);
+        let other_surface = other.surface_projection(axis);
+        if predicates::contains(&surface, &other_surface, predicates::EPSILON) {
+            ContactType::Superset
+        } else if predicates::contains(&other_surface, &surface, predicates::EPSILON) {
+            ContactType::Subset
+        } else if predicates::overlaps(&surface, &other_surface, predicates::EPSILON) {
+            ContactType::Mixed
+        } else {
+            ContactType::None
+        }
+    }
 }
 
 impl From<AABB> for Aabb3D {
@@ -217,6 +376,7 @@ impl From<AABB> for Aabb3D {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
@@ -226,7 +386,42 @@ pub enum Axis {
     NegZ,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Axis {
+    pub const ALL: [Axis; 6] = [
+        Axis::X,
+        Axis::Y,
+        Axis::Z,
+        Axis::NegX,
+        Axis::NegY,
+        Axis::NegZ,
+    ];
+
+    /// Index of the axis, 0 = x, 1 = y, 2 = z
+    pub fn axis(&self) -> usize {
+        match self {
+            Axis::X | Axis::NegX => 0,
+            Axis::Y | Axis::NegY => 1,
+            Axis::Z | Axis::NegZ => 2,
+        }
+    }
+
+    pub fn is_positive(&self) -> bool {
+        matches!(self, Axis::X | Axis::Y | Axis::Z)
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Axis::X => Axis::NegX,
+            Axis::Y => Axis::NegY,
+            Axis::Z => Axis::NegZ,
+            Axis::NegX => Axis::X,
+            Axis::NegY => Axis::Y,
+            Axis::NegZ => Axis::Z,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ContactType {
     Superset,
     Subset,
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Dimensions and movement abilities of the agent a nav mesh is built for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentParams {
+    /// Half of the agent width
+    pub radius: f32,
+    pub height: f32,
+    pub sneaking_height: f32,
+    /// Height which can be walked up without jumping
+    pub step_height: f32,
+    pub jump_height: f32,
+    /// Maximum fall without taking damage
+    pub max_drop: f32,
+    /// Maximum horizontal gap between floors which can be jumped
+    pub max_gap: f32,
+    pub link_costs: NavMeshLinkCosts,
+}
+
+impl AgentParams {
+    pub const PLAYER: Self = Self {
+        radius: 0.3,
+        height: 1.8,
+        sneaking_height: 1.5,
+        step_height: 0.6,
+        jump_height: 1.25,
+        max_drop: 3.0,
+        max_gap: 1.5,
+        link_costs: NavMeshLinkCosts::DEFAULT,
+    };
+
+    /// Same agent while sneaking
+    pub fn sneaking(&self) -> Self {
+        Self {
+            height: self.sneaking_height,
+            ..self.clone()
+        }
+    }
+}
+
+impl Default for AgentParams {
+    fn default() -> Self {
+        Self::PLAYER
+    }
+}
+
+/// Cost of crossing nav mesh links, in addition to the distance travelled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NavMeshLinkCosts {
+    pub step: f32,
+    pub jump: f32,
+    /// Cost per block fallen
+    pub drop: f32,
+    /// Cost per block jumped across, in addition to `jump`
+    pub gap: f32,
+}
+
+impl NavMeshLinkCosts {
+    pub const DEFAULT: Self = Self {
+        step: 0.5,
+        jump: 2.0,
+        drop: 0.5,
+        gap: 1.0,
+    };
+}
+
+impl Default for NavMeshLinkCosts {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::math::{IVec3, Vec3};
+
+use super::aabb_3d::{Aabb3D, Axis};
+use super::optimise_world::{SubChunk, SUB_CHUNK_SIZE};
+use super::predicates;
+
+/// First collision box hit by a ray
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayHit {
+    /// World position of the block the box belongs to
+    pub pos: IVec3,
+    /// Box that was hit, relative to `pos`
+    pub aabb: Aabb3D,
+    /// Face of the box that was hit
+    pub face: Axis,
+    /// Distance along the ray to the hit
+    pub distance: f32,
+}
+
+/// First collision box hit by a moving box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// Fraction of the motion completed before contact, between 0 and 1
+    pub time: f32,
+    /// Face of the box that was hit, pointing back at the moving box
+    pub normal: Axis,
+    /// World position of the block the box belongs to
+    pub pos: IVec3,
+    /// Box that was hit, relative to `pos`
+    pub aabb: Aabb3D,
+}
+
+/// Distances along a ray where it enters and leaves `aabb`, and the axis it enters through
+fn ray_aabb(origin: Vec3, dir: Vec3, aabb: &Aabb3D) -> Option<(f32, f32, usize)> {
+    let mut t_min = f32::NEG_INFINITY;
+    let mut t_max = f32::INFINITY;
+    let mut entry_axis = 0;
+
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            if origin[axis] < aabb.0[axis] || aabb.0[axis + 3] < origin[axis] {
+                return None;
+            }
+            continue;
+        }
+        let a = (aabb.0[axis] - origin[axis]) / dir[axis];
+        let b = (aabb.0[axis + 3] - origin[axis]) / dir[axis];
+        let (near, far) = if a < b { (a, b) } else { (b, a) };
+
+        if near > t_min {
+            t_min = near;
+            entry_axis = axis;
+        }
+        t_max = t_max.min(far);
+    }
+
+    if t_min <= t_max && t_max >= 0.0 {
+        Some((t_min, t_max, entry_axis))
+    } else {
+        None
+    }
+}
+
+/// Fraction of `motion` at which `moving` first touches `obstacle`, and the axis it touches on
+///
+/// Boxes that already overlap, or only slide along each other, don't collide.
+fn sweep_aabb(moving: &Aabb3D, motion: Vec3, obstacle: &Aabb3D) -> Option<(f32, usize)> {
+    let mut t_min = f32::NEG_INFINITY;
+    let mut t_max = f32::INFINITY;
+    let mut entry_axis = 0;
+
+    for axis in 0..3 {
+        // Offsets of `moving` along the axis for which the boxes overlap
+        let overlap_min = obstacle.0[axis] - moving.0[axis + 3];
+        let overlap_max = obstacle.0[axis + 3] - moving.0[axis];
+
+        if motion[axis] == 0.0 {
+            if overlap_min >= -predicates::EPSILON || overlap_max <= predicates::EPSILON {
+                return None;
+            }
+            continue;
+        }
+        let a = overlap_min / motion[axis];
+        let b = overlap_max / motion[axis];
+        let (near, far) = if a < b { (a, b) } else { (b, a) };
+
+        if near > t_min {
+            t_min = near;
+            entry_axis = axis;
+        }
+        t_max = t_max.min(far);
+    }
+
+    let already_touching = (t_min * motion[entry_axis]).abs() <= predicates::EPSILON;
+    if t_min < t_max && t_min <= 1.0 && (t_min >= 0.0 || already_touching) {
+        Some((t_min.max(0.0), entry_axis))
+    } else {
+        None
+    }
+}
+
+impl SubChunk {
+    pub fn origin(&self) -> Vec3 {
+        (self.location * SUB_CHUNK_SIZE).as_vec3()
+    }
+
+    /// Cast a ray from the world position `origin` and find the first collision box it hits
+    /// within `max_dist`. Only blocks inside the sub chunk are tested.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_dist: f32) -> Option<RayHit> {
+        let dir = dir.try_normalize()?;
+        let local = origin - self.origin();
+
+        // Start at the point where the ray enters the sub chunk
+        let bounds = Aabb3D([
+            0.0,
+            0.0,
+            0.0,
+            SUB_CHUNK_SIZE.x as f32,
+            SUB_CHUNK_SIZE.y as f32,
+            SUB_CHUNK_SIZE.z as f32,
+        ]);
+        let (enter, exit, _) = ray_aabb(local, dir, &bounds)?;
+        let mut t = enter.max(0.0);
+        let end = exit.min(max_dist);
+        if t > end {
+            return None;
+        }
+
+        // Voxel DDA, starting from the cell containing the entry point
+        let start = local + dir * t;
+        let mut cell = start
+            .floor()
+            .as_ivec3()
+            .clamp(IVec3::ZERO, SUB_CHUNK_SIZE - 1);
+        let step = dir.signum().as_ivec3();
+        let delta = (1.0 / dir).abs();
+        let mut next = Vec3::ZERO;
+        for axis in 0..3 {
+            next[axis] = if dir[axis] > 0.0 {
+                t + ((cell[axis] + 1) as f32 - start[axis]) * delta[axis]
+            } else if dir[axis] < 0.0 {
+                t + (start[axis] - cell[axis] as f32) * delta[axis]
+            } else {
+                f32::INFINITY
+            };
+        }
+
+        let mut best: Option<RayHit> = None;
+        while t <= end && SubChunk::contains(cell) {
+            let cell_exit = next.min_element();
+
+            for (pos, aabb) in self.iter_block(cell) {
+                let Some((near, _, axis)) = ray_aabb(local, dir, &aabb.translate(pos.as_vec3()))
+                else {
+                    continue;
+                };
+                let distance = near.max(0.0);
+                if distance > max_dist || best.as_ref().is_some_and(|hit| hit.distance <= distance)
+                {
+                    continue;
+                }
+
+                best = Some(RayHit {
+                    pos: pos + self.location * SUB_CHUNK_SIZE,
+                    aabb: aabb.clone(),
+                    face: Axis::ALL[axis + 3 * (dir[axis] > 0.0) as usize],
+                    distance,
+                });
+            }
+
+            // Boxes taller than a block can be hit beyond the current cell
+            if best.as_ref().is_some_and(|hit| hit.distance <= cell_exit) {
+                return best;
+            }
+
+            let axis = if next.x <= next.y && next.x <= next.z {
+                0
+            } else if next.y <= next.z {
+                1
+            } else {
+                2
+            };
+            t = next[axis];
+            next[axis] += delta[axis];
+            cell[axis] += step[axis];
+        }
+        best
+    }
+
+    /// Move the world space box `aabb` by `motion` and find the first collision box it touches.
+    /// Blocks outside the sub chunk and its halo are treated as empty.
+    pub fn sweep(&self, aabb: &Aabb3D, motion: Vec3) -> Option<Hit> {
+        if motion == Vec3::ZERO {
+            return None;
+        }
+        let local = aabb.translate(-self.origin());
+        let moved = local.translate(motion);
+
+        let min = Vec3::new(local.min_x(), local.min_y(), local.min_z()).min(Vec3::new(
+            moved.min_x(),
+            moved.min_y(),
+            moved.min_z(),
+        ));
+        let max = Vec3::new(local.max_x(), local.max_y(), local.max_z()).max(Vec3::new(
+            moved.max_x(),
+            moved.max_y(),
+            moved.max_z(),
+        ));
+
+        let mut best: Option<Hit> = None;
+        for z in min.z.floor() as i32..max.z.ceil() as i32 {
+            for x in min.x.floor() as i32..max.x.ceil() as i32 {
+                for y in min.y.floor() as i32..max.y.ceil() as i32 {
+                    for (pos, block_aabb) in self.iter_block(IVec3 { x, y, z }) {
+                        let Some((time, axis)) =
+                            sweep_aabb(&local, motion, &block_aabb.translate(pos.as_vec3()))
+                        else {
+                            continue;
+                        };
+                        if best.as_ref().is_some_and(|hit| hit.time <= time) {
+                            continue;
+                        }
+
+                        best = Some(Hit {
+                            time,
+                            normal: Axis::ALL[axis + 3 * (motion[axis] > 0.0) as usize],
+                            pos: pos + self.location * SUB_CHUNK_SIZE,
+                            aabb: block_aabb.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        best
+    }
+}
@@ -1,5 +1,13 @@
 pub mod aabb_2d;
 pub mod aabb_3d;
+pub mod agent;
+pub mod collision;
 pub mod debug_aabb_material;
 pub mod debug_surface_material;
+pub mod nav_build;
+pub mod nav_world;
 pub mod optimise_world;
+pub mod pathfind;
+pub mod predicates;
+pub mod serialise;
+pub mod snapshot;
@@ -0,0 +1,147 @@
+use bevy::{
+    ecs::{event::Event, system::Resource},
+    math::IVec3,
+    tasks::{block_on, AsyncComputeTaskPool, Task, TaskPool},
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    agent::AgentParams,
+    nav_world::{NavSubChunk, NavWorld},
+    serialise::SubChunkData,
+};
+
+/// A sub chunk and its nav mesh finished building in the background, it is held by
+/// `NavBuildTasks` until it is inserted with `NavBuildTasks::insert_built`
+#[derive(Event, Debug, Clone, Copy)]
+pub struct NavSubChunkBuilt(pub IVec3);
+
+/// Sub chunks and nav meshes being built on the async compute task pool
+///
+/// Collision data is copied out of the world while it is locked, the expensive part of building
+/// happens off the main thread. At most one task runs per sub chunk, starting another replaces it.
+/// Sub chunks marked dirty after they were copied are marked dirty again in the nav world once
+/// they are inserted, so the change isn't lost.
+#[derive(Resource)]
+pub struct NavBuildTasks {
+    agent: AgentParams,
+    tasks: HashMap<IVec3, Task<NavSubChunk>>,
+    /// Finished sub chunks waiting to be inserted into the nav world
+    built: HashMap<IVec3, NavSubChunk>,
+    /// Sub chunks which changed since they were copied out of the world
+    dirtied: HashSet<IVec3>,
+}
+
+impl NavBuildTasks {
+    pub fn new(agent: AgentParams) -> Self {
+        Self {
+            agent,
+            tasks: HashMap::new(),
+            built: HashMap::new(),
+            dirtied: HashSet::new(),
+        }
+    }
+
+    pub fn agent(&self) -> &AgentParams {
+        &self.agent
+    }
+
+    /// Number of tasks still running
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Whether the sub chunk is being built, or has been built and not yet inserted
+    pub fn contains(&self, index: IVec3) -> bool {
+        self.tasks.contains_key(&index) || self.built.contains_key(&index)
+    }
+
+    /// Start building a sub chunk, cancelling any task already building it
+    pub fn spawn(&mut self, data: SubChunkData) {
+        let agent = self.agent.clone();
+        let index = data.location;
+        let task = AsyncComputeTaskPool::get_or_init(TaskPool::default)
+            .spawn(async move { NavSubChunk::new(data.build(), &agent) });
+        self.tasks.insert(index, task);
+        self.built.remove(&index);
+        self.dirtied.remove(&index);
+    }
+
+    /// Stop building a sub chunk, returns whether it was being built
+    pub fn cancel(&mut self, index: IVec3) -> bool {
+        self.dirtied.remove(&index);
+        let running = self.tasks.remove(&index).is_some();
+        let built = self.built.remove(&index).is_some();
+        running || built
+    }
+
+    /// Cancel every sub chunk being built further than `radius` sub chunks from all of
+    /// `centres`, so it isn't inserted after the nav world evicted it. Returns the cancelled
+    /// indices.
+    pub fn evict(&mut self, centres: &[IVec3], radius: IVec3) -> Vec<IVec3> {
+        let evicted: Vec<IVec3> = self
+            .tasks
+            .keys()
+            .chain(self.built.keys())
+            .filter(|index| !NavWorld::is_near(**index, centres, radius))
+            .copied()
+            .collect();
+        for index in evicted.iter() {
+            self.cancel(*index);
+        }
+        evicted
+    }
+
+    /// Record that a sub chunk changed, ignored if it isn't being built
+    pub fn mark_dirty(&mut self, index: IVec3) {
+        if self.contains(index) {
+            self.dirtied.insert(index);
+        }
+    }
+
+    /// Whether a sub chunk being built changed since it was copied
+    pub fn is_dirty(&self, index: IVec3) -> bool {
+        self.dirtied.contains(&index)
+    }
+
+    /// Collect the tasks which have finished, returning the indices of their sub chunks
+    pub fn poll(&mut self) -> Vec<IVec3> {
+        let finished: Vec<IVec3> = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(index, _)| *index)
+            .collect();
+
+        for index in finished.iter() {
+            if let Some(task) = self.tasks.remove(index) {
+                self.built.insert(*index, block_on(task));
+            }
+        }
+        finished
+    }
+
+    /// Take a finished sub chunk without inserting it, it is no longer tracked
+    pub fn take_built(&mut self, index: IVec3) -> Option<NavSubChunk> {
+        self.dirtied.remove(&index);
+        self.built.remove(&index)
+    }
+
+    /// Move a finished sub chunk into the nav world, marking it dirty there if it changed while
+    /// it was being built. Returns whether there was a finished sub chunk at `index`.
+    pub fn insert_built(&mut self, nav_world: &mut NavWorld, index: IVec3) -> bool {
+        let dirty = self.dirtied.contains(&index);
+        let Some(nav_sub_chunk) = self.take_built(index) else {
+            return false;
+        };
+        nav_world.insert_built(nav_sub_chunk);
+        if dirty {
+            nav_world.mark_dirty(index);
+        }
+        true
+    }
+}
@@ -0,0 +1,478 @@
+use bevy::{
+    ecs::system::Resource,
+    math::{IVec3, Vec2, Vec3, Vec3Swizzles},
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    aabb_2d::Aabb2D,
+    agent::AgentParams,
+    optimise_world::{
+        NavMeshAdjacent, NavMeshLink, NavMeshNode, NavNodeId, SubChunk, SubChunkHalo,
+        SubChunkNavMesh, SUB_CHUNK_SIZE,
+    },
+    pathfind::{NavEdge, NavGraph, Portal},
+};
+
+/// Node in a `NavWorld`, identified by its sub chunk and its index within the sub chunk nav mesh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NavWorldNode {
+    pub sub_chunk: IVec3,
+    pub node: NavNodeId,
+}
+
+#[derive(Clone)]
+pub struct NavSubChunk {
+    pub sub_chunk: SubChunk,
+    pub nav_mesh: SubChunkNavMesh,
+}
+
+impl NavSubChunk {
+    pub fn new(sub_chunk: SubChunk, agent: &AgentParams) -> Self {
+        let nav_mesh = sub_chunk.build_nav_mesh(agent);
+        Self {
+            sub_chunk,
+            nav_mesh,
+        }
+    }
+}
+
+/// Sub chunks and their nav meshes, keyed by sub chunk index
+#[derive(Resource)]
+pub struct NavWorld {
+    agent: AgentParams,
+    sub_chunks: HashMap<IVec3, NavSubChunk>,
+    /// Sub chunks which are out of date and need to be rebuilt
+    dirty: HashSet<IVec3>,
+}
+
+impl Default for NavWorld {
+    fn default() -> Self {
+        Self::new(AgentParams::default())
+    }
+}
+
+impl NavWorld {
+    pub fn new(agent: AgentParams) -> Self {
+        Self {
+            agent,
+            sub_chunks: HashMap::new(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    pub fn agent(&self) -> &AgentParams {
+        &self.agent
+    }
+
+    /// Index of the sub chunk containing the world position `pos`
+    pub fn sub_chunk_index(pos: Vec3) -> IVec3 {
+        pos.floor().as_ivec3().div_euclid(SUB_CHUNK_SIZE)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sub_chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sub_chunks.is_empty()
+    }
+
+    pub fn contains(&self, index: IVec3) -> bool {
+        self.sub_chunks.contains_key(&index)
+    }
+
+    pub fn get(&self, index: IVec3) -> Option<&NavSubChunk> {
+        self.sub_chunks.get(&index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &NavSubChunk> {
+        self.sub_chunks.values()
+    }
+
+    /// Build the nav mesh for a sub chunk, replacing any existing entry at its location
+    pub fn insert(&mut self, sub_chunk: SubChunk) -> &NavSubChunk {
+        self.dirty.remove(&sub_chunk.location);
+        let nav_sub_chunk = NavSubChunk::new(sub_chunk, &self.agent);
+        self.insert_built(nav_sub_chunk)
+    }
+
+    /// Insert a sub chunk with an already built nav mesh, replacing any existing entry at its
+    /// location. The nav mesh should have been built for the agent of the nav world.
+    ///
+    /// Unlike `insert` the sub chunk stays dirty if it was marked while it was being built, as
+    /// it may have been copied from the world before the change.
+    pub fn insert_built(&mut self, nav_sub_chunk: NavSubChunk) -> &NavSubChunk {
+        let location = nav_sub_chunk.sub_chunk.location;
+        self.sub_chunks.insert(location, nav_sub_chunk);
+        &self.sub_chunks[&location]
+    }
+
+    /// Get a sub chunk, building it with `build` if it isn't already present.
+    /// `build` can return `None` if the sub chunk isn't loaded.
+    pub fn get_or_insert_with(
+        &mut self,
+        index: IVec3,
+        build: impl FnOnce() -> Option<SubChunk>,
+    ) -> Option<&NavSubChunk> {
+        if !self.contains(index) {
+            self.insert(build()?);
+        }
+        self.sub_chunks.get(&index)
+    }
+
+    pub fn remove(&mut self, index: IVec3) -> Option<NavSubChunk> {
+        self.dirty.remove(&index);
+        self.sub_chunks.remove(&index)
+    }
+
+    /// Whether the sub chunk is within `radius` sub chunks of any of `centres` along every axis
+    pub fn is_near(index: IVec3, centres: &[IVec3], radius: IVec3) -> bool {
+        centres
+            .iter()
+            .any(|centre| (index - *centre).abs().cmple(radius).all())
+    }
+
+    /// Remove every sub chunk further than `radius` sub chunks from all of `centres` along any
+    /// axis, returning the indices which were removed
+    pub fn evict(&mut self, centres: &[IVec3], radius: IVec3) -> Vec<IVec3> {
+        let evicted: Vec<IVec3> = self
+            .sub_chunks
+            .keys()
+            .filter(|index| !Self::is_near(**index, centres, radius))
+            .copied()
+            .collect();
+        for index in evicted.iter() {
+            self.remove(*index);
+        }
+        evicted
+    }
+
+    /// Mark a sub chunk as needing to be rebuilt, ignored if it hasn't been built
+    pub fn mark_dirty(&mut self, index: IVec3) {
+        if self.contains(index) {
+            self.dirty.insert(index);
+        }
+    }
+
+    /// Mark every sub chunk using the block at the world position `pos` as needing to be
+    /// rebuilt, including neighbours which have it in their halo
+    pub fn mark_block_dirty(&mut self, pos: IVec3) {
+        for index in self.block_sub_chunks(pos) {
+            self.mark_dirty(index);
+        }
+    }
+
+    /// Sub chunks using the block at the world position `pos`, the one containing it and
+    /// neighbours which have it in their halo
+    pub fn block_sub_chunks(&self, pos: IVec3) -> impl Iterator<Item = IVec3> {
+        let index = pos.div_euclid(SUB_CHUNK_SIZE);
+        let halo = SubChunkHalo::new(&self.agent);
+        Self::indices(index - IVec3::ONE, index + IVec3::ONE).filter(move |neighbour| {
+            *neighbour == index || halo.contains(pos - *neighbour * SUB_CHUNK_SIZE)
+        })
+    }
+
+    pub fn is_dirty(&self, index: IVec3) -> bool {
+        self.dirty.contains(&index)
+    }
+
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Take up to `budget` sub chunks which need to be rebuilt, they are no longer marked as dirty
+    pub fn take_dirty(&mut self, budget: usize) -> Vec<IVec3> {
+        let taken: Vec<IVec3> = self.dirty.iter().take(budget).copied().collect();
+        for index in taken.iter() {
+            self.dirty.remove(index);
+        }
+        taken
+    }
+
+    /// Take up to `budget` sub chunks which need to be rebuilt, nearest to any of `centres` along
+    /// the furthest axis first. They are no longer marked as dirty.
+    pub fn take_dirty_nearest(&mut self, centres: &[IVec3], budget: usize) -> Vec<IVec3> {
+        let mut taken: Vec<IVec3> = self.dirty.iter().copied().collect();
+        taken.sort_by_key(|index| {
+            let distance = centres
+                .iter()
+                .map(|centre| (*index - *centre).abs().max_element())
+                .min();
+            (distance, index.to_array())
+        });
+        taken.truncate(budget);
+        for index in taken.iter() {
+            self.dirty.remove(index);
+        }
+        taken
+    }
+
+    pub fn node(&self, node: NavWorldNode) -> Option<&NavMeshNode> {
+        self.sub_chunks
+            .get(&node.sub_chunk)?
+            .nav_mesh
+            .floor
+            .get(node.node.layer)?
+            .nodes
+            .get(node.node.node)
+    }
+
+    /// World height of the floor of `node`
+    pub fn node_height(&self, node: NavWorldNode) -> Option<f32> {
+        let nav_mesh = &self.sub_chunks.get(&node.sub_chunk)?.nav_mesh;
+        Some(nav_mesh.origin().y + nav_mesh.floor.get(node.node.layer)?.height)
+    }
+
+    /// Highest floor node below the world position `pos`
+    pub fn node_at(&self, pos: Vec3) -> Option<NavWorldNode> {
+        // Nodes extend past their sub chunk by the agent radius, and floors are below the position
+        let reach = Vec3 {
+            x: self.agent.radius,
+            y: 0.0,
+            z: self.agent.radius,
+        };
+        let min =
+            Self::sub_chunk_index(pos - reach - Vec3::Y * SubChunkNavMesh::NODE_HEIGHT_TOLERANCE);
+        let max = Self::sub_chunk_index(pos + reach);
+
+        Self::indices(min, max)
+            .flat_map(|index| {
+                let nav_mesh = &self.sub_chunks.get(&index)?.nav_mesh;
+                nav_mesh.node_at(pos).map(|node| {
+                    (
+                        nav_mesh.origin().y + nav_mesh.floor[node.layer].height,
+                        NavWorldNode {
+                            sub_chunk: index,
+                            node,
+                        },
+                    )
+                })
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, node)| node)
+    }
+
+    /// Floor nodes overlapping the world region between `min` and `max`
+    pub fn nodes_in_region(&self, min: Vec3, max: Vec3) -> Vec<NavWorldNode> {
+        let region = Aabb2D {
+            min_x: min.x,
+            min_y: min.z,
+            max_x: max.x,
+            max_y: max.z,
+        };
+        let reach = Vec3 {
+            x: self.agent.radius,
+            y: 0.0,
+            z: self.agent.radius,
+        };
+
+        let mut nodes = vec![];
+        for index in Self::indices(
+            Self::sub_chunk_index(min - reach),
+            Self::sub_chunk_index(max + reach),
+        ) {
+            let Some(NavSubChunk { nav_mesh, .. }) = self.sub_chunks.get(&index) else {
+                continue;
+            };
+            let origin = nav_mesh.origin();
+
+            for (layer_index, layer) in nav_mesh.floor.iter().enumerate() {
+                let height = origin.y + layer.height;
+                if height < min.y || max.y < height {
+                    continue;
+                }
+
+                for (node_index, node) in layer.nodes.iter().enumerate() {
+                    let aabb = node.layer_aabb().translate(origin.xz());
+                    if aabb.min_x < region.max_x
+                        && region.min_x < aabb.max_x
+                        && aabb.min_y < region.max_y
+                        && region.min_y < aabb.max_y
+                    {
+                        nodes.push(NavWorldNode {
+                            sub_chunk: index,
+                            node: NavNodeId {
+                                layer: layer_index,
+                                node: node_index,
+                            },
+                        });
+                    }
+                }
+            }
+        }
+        nodes
+    }
+
+    fn indices(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+        (min.z..=max.z).flat_map(move |z| {
+            (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| IVec3 { x, y, z }))
+        })
+    }
+
+    /// Connections from a node to nodes in neighbouring sub chunks, including the ones above and
+    /// below. Nodes at the same height are walked to, other nodes are linked by the same rules
+    /// as nodes within a sub chunk.
+    fn boundary_successors(&self, node: NavWorldNode) -> Vec<NavEdge<NavWorldNode>> {
+        const EPSILON: f32 = 1e-4;
+
+        let mut edges = vec![];
+        let Some(NavSubChunk {
+            sub_chunk,
+            nav_mesh,
+        }) = self.sub_chunks.get(&node.sub_chunk)
+        else {
+            return edges;
+        };
+        let agent = &self.agent;
+        let layer = &nav_mesh.floor[node.node.layer];
+        let aabb = layer.nodes[node.node.node].layer_aabb();
+        let size = SUB_CHUNK_SIZE.as_vec3();
+
+        for index in Self::indices(node.sub_chunk - IVec3::ONE, node.sub_chunk + IVec3::ONE) {
+            let delta = index - node.sub_chunk;
+            let near_boundary = |delta: i32, min: f32, max: f32, size: f32| match delta {
+                -1 => min <= agent.max_gap + EPSILON,
+                1 => max >= size - agent.max_gap - EPSILON,
+                _ => true,
+            };
+            if delta == IVec3::ZERO
+                || !near_boundary(delta.x, aabb.min_x, aabb.max_x, size.x)
+                || !near_boundary(delta.z, aabb.min_y, aabb.max_y, size.z)
+            {
+                continue;
+            }
+
+            let Some(NavSubChunk {
+                sub_chunk: neighbour_sub_chunk,
+                nav_mesh: neighbour,
+            }) = self.sub_chunks.get(&index)
+            else {
+                continue;
+            };
+
+            // Node area and height relative to the neighbour
+            let offset = nav_mesh.origin() - neighbour.origin();
+            let local = aabb.translate(offset.xz());
+            let height = layer.height + offset.y;
+
+            for (neighbour_layer_index, neighbour_layer) in neighbour.floor.iter().enumerate() {
+                let height_delta = neighbour_layer.height - height;
+                if height_delta > agent.jump_height || -height_delta > agent.max_drop {
+                    continue;
+                }
+
+                for neighbour_node_index in
+                    neighbour_layer.nodes_near(&local.inflate(Vec2::splat(agent.max_gap)))
+                {
+                    let neighbour_aabb = neighbour_layer.nodes[neighbour_node_index].layer_aabb();
+                    let to = NavWorldNode {
+                        sub_chunk: index,
+                        node: NavNodeId {
+                            layer: neighbour_layer_index,
+                            node: neighbour_node_index,
+                        },
+                    };
+
+                    if height_delta.abs() <= EPSILON {
+                        if let Some((adjacent, _)) = NavMeshAdjacent::between(
+                            &local,
+                            0,
+                            &neighbour_aabb,
+                            neighbour_node_index,
+                        ) {
+                            edges.push(NavEdge {
+                                node: to,
+                                portal: Portal::crossing(
+                                    &local,
+                                    &neighbour_aabb,
+                                    &adjacent,
+                                    neighbour_layer.height,
+                                    neighbour.origin(),
+                                ),
+                                cost: 0.0,
+                            });
+                        }
+                        continue;
+                    }
+
+                    // Each sub chunk only sees one block past its border, so both have to find
+                    // the space crossed by the link clear
+                    let to_height = neighbour_layer.height - offset.y;
+                    let Some((link_type, cost, axis, value, min, max)) = sub_chunk.link_nodes(
+                        &aabb,
+                        layer.height,
+                        &neighbour_aabb.translate(-offset.xz()),
+                        to_height,
+                        false,
+                        agent,
+                    ) else {
+                        continue;
+                    };
+                    if neighbour_sub_chunk
+                        .link_nodes(
+                            &local,
+                            height,
+                            &neighbour_aabb,
+                            neighbour_layer.height,
+                            false,
+                            agent,
+                        )
+                        .is_none()
+                    {
+                        continue;
+                    }
+
+                    let link = NavMeshLink {
+                        to: to.node,
+                        link_type,
+                        cost,
+                        axis,
+                        value,
+                        min,
+                        max,
+                    };
+                    edges.push(NavEdge {
+                        node: to,
+                        portal: Portal::from_link(&link, to_height, nav_mesh.origin()),
+                        cost,
+                    });
+                }
+            }
+        }
+        edges
+    }
+}
+
+impl NavGraph for NavWorld {
+    type Node = NavWorldNode;
+
+    fn node_at(&self, pos: Vec3) -> Option<NavWorldNode> {
+        NavWorld::node_at(self, pos)
+    }
+
+    fn height(&self, node: NavWorldNode) -> f32 {
+        self.node_height(node).unwrap_or(f32::NAN)
+    }
+
+    fn successors(&self, node: NavWorldNode) -> Vec<NavEdge<NavWorldNode>> {
+        let Some(NavSubChunk { nav_mesh, .. }) = self.sub_chunks.get(&node.sub_chunk) else {
+            return vec![];
+        };
+
+        let mut edges: Vec<NavEdge<NavWorldNode>> = nav_mesh
+            .successors(node.node)
+            .into_iter()
+            .map(|edge| NavEdge {
+                node: NavWorldNode {
+                    sub_chunk: node.sub_chunk,
+                    node: edge.node,
+                },
+                portal: edge.portal,
+                cost: edge.cost,
+            })
+            .collect();
+        edges.extend(self.boundary_successors(node));
+        edges
+    }
+}
@@ -1,11 +1,17 @@
 use std::f32::consts::E;
 
-use bevy::math::{IVec2, IVec3, UVec2, UVec3, Vec2};
+use bevy::{
+    math::{IVec2, IVec3, UVec2, UVec3, Vec2, Vec3},
+    utils::HashMap,
+};
 
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 
-use super::aabb_2d::Aabb2D;
+use super::aabb_2d::{Aabb2D, Point2D};
 use super::aabb_3d::Aabb3D;
+use super::agent::AgentParams;
+use super::predicates;
 
 pub const CHUNK_WIDTH: usize = 16;
 pub const SUB_CHUNK_HEIGHT: usize = 16;
@@ -16,20 +22,150 @@ pub const SUB_CHUNK_SIZE: IVec3 = IVec3 {
     z: CHUNK_WIDTH as i32,
 };
 
+/// Collision boxes of each block in a sub chunk, indexed `[z][x][y]`
+pub type SubChunkSource =
+    Box<[[[SmallVec<[Aabb3D; 1]>; SUB_CHUNK_HEIGHT]; CHUNK_WIDTH]; CHUNK_WIDTH]>;
+
 // Index order: data[z][x][y]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SubChunkNavMesh {
     pub location: IVec3,
+    pub agent: AgentParams,
     pub floor: Box<[NavMeshLayer]>,
     pub ceiling: Box<[NavMeshLayer]>,
 }
 
+impl SubChunkNavMesh {
+    /// Distance above a floor that a position can be and still be on it
+    pub const NODE_HEIGHT_TOLERANCE: f32 = 1.0;
+
+    pub fn origin(&self) -> Vec3 {
+        (self.location * SUB_CHUNK_SIZE).as_vec3()
+    }
+
+    /// Highest floor node below the world position `pos`
+    pub fn node_at(&self, pos: Vec3) -> Option<NavNodeId> {
+        let local = pos - self.origin();
+        let point = Point2D {
+            x: local.x,
+            y: local.z,
+        };
+
+        self.floor
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, layer)| {
+                layer.height <= local.y + 0.1
+                    && local.y - layer.height < Self::NODE_HEIGHT_TOLERANCE
+            })
+            .find_map(|(layer_index, layer)| {
+                layer
+                    .nodes
+                    .iter()
+                    .position(|node| node.layer_aabb().contains(&point))
+                    .map(|node| NavNodeId {
+                        layer: layer_index,
+                        node,
+                    })
+            })
+    }
+
+    /// Check the nav mesh for inconsistencies, returning a description of each problem found
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+
+        for (index, pair) in self.floor.windows(2).enumerate() {
+            if pair[0].height >= pair[1].height {
+                errors.push(format!(
+                    "Floor layers {} and {} aren't sorted by height",
+                    index,
+                    index + 1
+                ));
+            }
+        }
+
+        for (layer_index, layer) in self.ceiling.iter().enumerate() {
+            layer.validate_blocks(&format!("Ceiling layer {}", layer_index), &mut errors);
+        }
+
+        for (layer_index, layer) in self.floor.iter().enumerate() {
+            layer.validate_blocks(&format!("Floor layer {}", layer_index), &mut errors);
+            let aabbs: Vec<Aabb2D> = layer.nodes.iter().map(|node| node.layer_aabb()).collect();
+
+            for (node_index, node) in layer.nodes.iter().enumerate() {
+                let id = NavNodeId {
+                    layer: layer_index,
+                    node: node_index,
+                };
+
+                if node.aabb.clone().validate().is_none() {
+                    errors.push(format!("{:?} has an empty area {:?}", id, node.aabb));
+                }
+                if NavMeshLayer::cells(&node.layer_aabb())
+                    .any(|(x, z)| !layer.blocks[z][x].contains(&node_index))
+                {
+                    errors.push(format!("{:?} is missing from the block index", id));
+                }
+
+                for other_index in node_index + 1..aabbs.len() {
+                    if aabbs[node_index].overlaps(&aabbs[other_index]) {
+                        errors.push(format!(
+                            "{:?} overlaps node {} of the same layer",
+                            id, other_index
+                        ));
+                    }
+                }
+
+                for adjacent in node.adjacent.iter() {
+                    let other = layer.nodes.get(adjacent.index());
+                    if !other.is_some_and(|other| {
+                        other.adjacent.iter().any(|back| back.index() == node_index)
+                    }) {
+                        errors.push(format!(
+                            "{:?} is adjacent to node {} which doesn't link back",
+                            id,
+                            adjacent.index()
+                        ));
+                    }
+                }
+
+                for link in node.links.iter() {
+                    let target = self
+                        .floor
+                        .get(link.to.layer)
+                        .and_then(|layer| layer.nodes.get(link.to.node));
+                    if target.is_none() {
+                        errors.push(format!("{:?} links to missing node {:?}", id, link.to));
+                    }
+                    if link.cost.is_nan() || link.cost < 0.0 {
+                        errors.push(format!(
+                            "{:?} has a link to {:?} with cost {}",
+                            id, link.to, link.cost
+                        ));
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+/// Index of a floor node within a sub chunk nav mesh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NavNodeId {
+    pub layer: usize,
+    pub node: usize,
+}
+
 #[derive(Debug)]
 pub enum NavMeshLayerType {
     Floor,
     Ceiling,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NavMeshLayer {
     pub height: f32,
     pub nodes: Vec<NavMeshNode>,
@@ -41,31 +177,172 @@ impl NavMeshLayer {
         let node = NavMeshNode {
             aabb,
             pos,
-            _adjacent: smallvec![],
+            adjacent: smallvec![],
+            links: smallvec![],
         };
-        self.blocks[pos.y as usize][pos.x as usize].push(self.nodes.len());
+        for (x, z) in Self::cells(&node.layer_aabb()) {
+            self.blocks[z][x].push(self.nodes.len());
+        }
         self.nodes.push(node);
     }
+
+    /// Block index cells covered by a rectangle relative to the sub chunk, as `(x, z)` pairs
+    ///
+    /// Parts of the rectangle outside of the sub chunk are counted in the cells on its border.
+    fn cells(aabb: &Aabb2D) -> impl Iterator<Item = (usize, usize)> {
+        let range = |min: f32, max: f32| {
+            let last = CHUNK_WIDTH as i32 - 1;
+            let start = (min.floor() as i32).clamp(0, last);
+            let end = (max.ceil() as i32 - 1).clamp(start, last);
+            start as usize..=end as usize
+        };
+        let xs = range(aabb.min_x, aabb.max_x);
+        range(aabb.min_y, aabb.max_y).flat_map(move |z| xs.clone().map(move |x| (x, z)))
+    }
+
+    /// Indices of the nodes which may overlap or touch a rectangle relative to the sub chunk,
+    /// in ascending order
+    pub(super) fn nodes_near(&self, aabb: &Aabb2D) -> Vec<usize> {
+        let mut indices: Vec<usize> = Self::cells(&aabb.inflate(Vec2::splat(predicates::EPSILON)))
+            .flat_map(|(x, z)| self.blocks[z][x].iter().copied())
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Check that nodes are inside the sub chunk and the block index only refers to existing nodes
+    fn validate_blocks(&self, name: &str, errors: &mut Vec<String>) {
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.pos.x as usize >= CHUNK_WIDTH || node.pos.y as usize >= CHUNK_WIDTH {
+                errors.push(format!(
+                    "{} node {} is outside the sub chunk at {}",
+                    name, index, node.pos
+                ));
+            }
+        }
+        for (z, row) in self.blocks.iter().enumerate() {
+            for (x, block) in row.iter().enumerate() {
+                for index in block.iter().filter(|index| **index >= self.nodes.len()) {
+                    errors.push(format!(
+                        "{} block [{}, {}] refers to missing node {}",
+                        name, x, z, index
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Greedily merge nodes which share a whole edge into larger rectangles
+    ///
+    /// Merge candidates are found with the block index, a merged node is added to the cells it
+    /// grows into and tried again until nothing else merges with it.
+    fn merge_nodes(&mut self) {
+        let mut nodes: Vec<Option<(UVec2, Aabb2D)>> = self
+            .nodes
+            .drain(0..self.nodes.len())
+            .map(|node| Some((node.pos, node.layer_aabb())))
+            .collect();
+
+        let mut pending: Vec<usize> = (0..nodes.len()).rev().collect();
+        while let Some(a) = pending.pop() {
+            let Some((a_pos, a_aabb)) = nodes[a].clone() else {
+                continue;
+            };
+            let merged = self
+                .nodes_near(&a_aabb)
+                .into_iter()
+                .filter(|b| *b != a)
+                .find_map(|b| {
+                    let (b_pos, b_aabb) = nodes[b].as_ref()?;
+                    a_aabb
+                        .merge(b_aabb)
+                        .map(|aabb| (b, a_pos.min(*b_pos), aabb))
+                });
+
+            if let Some((b, pos, aabb)) = merged {
+                for (x, z) in Self::cells(&aabb) {
+                    if !self.blocks[z][x].contains(&a) {
+                        self.blocks[z][x].push(a);
+                    }
+                }
+                nodes[a] = Some((pos, aabb));
+                nodes[b] = None;
+                pending.push(a);
+            }
+        }
+
+        for block in self.blocks.iter_mut().flatten() {
+            block.clear();
+        }
+        for (pos, aabb) in nodes.into_iter().flatten() {
+            let aabb = aabb.translate(-pos.as_vec2());
+            if aabb.is_sliver() {
+                continue;
+            }
+            self.insert(aabb, pos);
+        }
+    }
+
+    /// Link nodes which share an edge, or overlap, with each other
+    fn link_adjacent(&mut self) {
+        let aabbs: Vec<Aabb2D> = self.nodes.iter().map(|node| node.layer_aabb()).collect();
+
+        for node in self.nodes.iter_mut() {
+            node.adjacent.clear();
+        }
+
+        // Candidates come from the block index, in ascending order so each pair is tried once
+        for a in 0..aabbs.len() {
+            for b in self.nodes_near(&aabbs[a]).into_iter().filter(|b| *b > a) {
+                if let Some((a_adjacent, b_adjacent)) =
+                    NavMeshAdjacent::between(&aabbs[a], a, &aabbs[b], b)
+                {
+                    self.nodes[a].adjacent.push(a_adjacent);
+                    self.nodes[b].adjacent.push(b_adjacent);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NavMeshNode {
     pub aabb: Aabb2D,
     pub pos: UVec2,
-    pub _adjacent: SmallVec<[NavMeshAdjacent; 0]>,
+    pub adjacent: SmallVec<[NavMeshAdjacent; 0]>,
+    pub links: SmallVec<[NavMeshLink; 0]>,
+}
+
+impl NavMeshNode {
+    /// Node area relative to the sub chunk rather than the node block
+    pub fn layer_aabb(&self) -> Aabb2D {
+        self.aabb.translate(self.pos.as_vec2())
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Connection to another node in the same layer.
+///
+/// `axis` is the edge of this node the connection crosses, `axis % 2` is the axis (0 = x, 1 = z)
+/// and `axis / 2` is the side (0 = min, 1 = max). `min` and `max` bound the shared edge along the
+/// other axis, relative to the sub chunk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NavMeshAdjacent {
-    _Superset {
+    /// The shared edge covers all of the other node's edge
+    Superset {
+        min: f32,
+        max: f32,
         index: usize,
         axis: u8,
     },
-    _Subset {
+    /// The shared edge covers all of this node's edge
+    Subset {
+        min: f32,
+        max: f32,
         index: usize,
         axis: u8,
     },
-    _Overlapping {
+    Overlapping {
         min: f32,
         max: f32,
         index: usize,
@@ -73,20 +350,265 @@ pub enum NavMeshAdjacent {
     },
 }
 
+impl NavMeshAdjacent {
+    const EPSILON: f32 = 1e-4;
+
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Superset { index, .. }
+            | Self::Subset { index, .. }
+            | Self::Overlapping { index, .. } => *index,
+        }
+    }
+
+    pub fn axis(&self) -> u8 {
+        match self {
+            Self::Superset { axis, .. }
+            | Self::Subset { axis, .. }
+            | Self::Overlapping { axis, .. } => *axis,
+        }
+    }
+
+    pub fn interval(&self) -> (f32, f32) {
+        match self {
+            Self::Superset { min, max, .. }
+            | Self::Subset { min, max, .. }
+            | Self::Overlapping { min, max, .. } => (*min, *max),
+        }
+    }
+
+    /// Connections for a pair of nodes, as seen from `a` and from `b`.
+    ///
+    /// Nodes that touch along an edge are connected across that edge, overlapping nodes are
+    /// connected across the axis with the least overlap. Nodes only touching at a corner aren't
+    /// connected.
+    pub fn between(a: &Aabb2D, a_index: usize, b: &Aabb2D, b_index: usize) -> Option<(Self, Self)> {
+        let a_array = a.to_array();
+        let b_array = b.to_array();
+
+        let overlap = [0, 1].map(|axis| {
+            a_array[1][axis].min(b_array[1][axis]) - a_array[0][axis].max(b_array[0][axis])
+        });
+        if overlap.iter().any(|v| *v < -Self::EPSILON) {
+            return None;
+        }
+
+        let axis = if overlap[0] <= overlap[1] { 0 } else { 1 };
+        let edge_axis = 1 - axis;
+        if overlap[edge_axis] <= Self::EPSILON {
+            return None;
+        }
+
+        let min = a_array[0][edge_axis].max(b_array[0][edge_axis]);
+        let max = a_array[1][edge_axis].min(b_array[1][edge_axis]);
+
+        // Side of a facing b
+        let a_centre = a_array[0][axis] + a_array[1][axis];
+        let b_centre = b_array[0][axis] + b_array[1][axis];
+        let a_dir = (a_centre < b_centre) as u8;
+
+        let classify = |edge: [f32; 2], other_edge: [f32; 2], index: usize, dir: u8| {
+            let axis = axis as u8 + 2 * dir;
+            if (other_edge[0] - min).abs() <= Self::EPSILON
+                && (other_edge[1] - max).abs() <= Self::EPSILON
+            {
+                Self::Superset {
+                    min,
+                    max,
+                    index,
+                    axis,
+                }
+            } else if (edge[0] - min).abs() <= Self::EPSILON
+                && (edge[1] - max).abs() <= Self::EPSILON
+            {
+                Self::Subset {
+                    min,
+                    max,
+                    index,
+                    axis,
+                }
+            } else {
+                Self::Overlapping {
+                    min,
+                    max,
+                    index,
+                    axis,
+                }
+            }
+        };
+
+        let a_edge = [a_array[0][edge_axis], a_array[1][edge_axis]];
+        let b_edge = [b_array[0][edge_axis], b_array[1][edge_axis]];
+
+        Some((
+            classify(a_edge, b_edge, b_index, a_dir),
+            classify(b_edge, a_edge, a_index, 1 - a_dir),
+        ))
+    }
+}
+
+/// Movement between nodes other than walking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NavMeshLinkType {
+    StepUp,
+    JumpUp,
+    Drop,
+    GapJump,
+}
+
+/// Off mesh connection to a node in another layer, or across a gap.
+///
+/// `axis`, `min` and `max` describe the crossed edge in the same way as `NavMeshAdjacent`, and
+/// `value` is the position of the crossing along the axis, relative to the sub chunk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NavMeshLink {
+    pub to: NavNodeId,
+    pub link_type: NavMeshLinkType,
+    pub cost: f32,
+    pub axis: u8,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Collision data for the blocks surrounding a sub chunk, positioned relative to the sub chunk origin.
+///
+/// Sized for an agent by `SubChunkHalo::new`: a border as wide as the agent's reach on the sides,
+/// one block below, and enough blocks above to check headroom for floors at the top of the sub
+/// chunk. The default halo is sized for `AgentParams::PLAYER`.
+#[derive(Clone)]
+pub struct SubChunkHalo {
+    min: IVec3,
+    max: IVec3,
+    aabbs: HashMap<IVec3, SmallVec<[Aabb3D; 1]>>,
+}
+
+impl Default for SubChunkHalo {
+    fn default() -> Self {
+        Self::new(&AgentParams::PLAYER)
+    }
+}
+
+impl SubChunkHalo {
+    pub fn new(agent: &AgentParams) -> Self {
+        let (min, max) = Self::bounds(agent);
+        Self::with_bounds(min, max)
+    }
+
+    /// Empty halo covering the blocks from `min` to `max` inclusive outside of the sub chunk.
+    /// The bounds are clamped to the neighbouring sub chunks.
+    pub fn with_bounds(min: IVec3, max: IVec3) -> Self {
+        Self {
+            min: min.max(-SUB_CHUNK_SIZE),
+            max: max.min(SUB_CHUNK_SIZE * 2 - IVec3::ONE),
+            aabbs: Default::default(),
+        }
+    }
+
+    /// Inclusive bounds of the halo needed to build a nav mesh for `agent`.
+    ///
+    /// Obstacles cut floor nodes up to `ceil(2 * radius)` blocks away, and floors at the top of
+    /// the sub chunk need `ceil(height)` blocks of headroom above it.
+    pub fn bounds(agent: &AgentParams) -> (IVec3, IVec3) {
+        let reach = ((2.0 * agent.radius).ceil() as i32).max(1);
+        let above = (agent.height.ceil() as i32).max(1);
+        (
+            IVec3::new(-reach, -1, -reach),
+            IVec3::new(
+                CHUNK_WIDTH as i32 - 1 + reach,
+                SUB_CHUNK_HEIGHT as i32 - 1 + above,
+                CHUNK_WIDTH as i32 - 1 + reach,
+            ),
+        )
+    }
+
+    /// Inclusive lower bound of the halo
+    pub fn min(&self) -> IVec3 {
+        self.min
+    }
+
+    /// Inclusive upper bound of the halo
+    pub fn max(&self) -> IVec3 {
+        self.max
+    }
+
+    pub fn contains(&self, pos: IVec3) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all() && !SubChunk::contains(pos)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = IVec3> + '_ {
+        let (min, max) = (self.min, self.max);
+        (min.z..=max.z)
+            .flat_map(move |z| {
+                (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| IVec3 { x, y, z }))
+            })
+            .filter(|pos| self.contains(*pos))
+    }
+
+    /// Set the collision boxes of a halo block, replacing any previous value.
+    /// Positions outside of the halo are ignored.
+    pub fn insert(&mut self, pos: IVec3, source: SmallVec<[Aabb3D; 1]>) {
+        if !self.contains(pos) {
+            return;
+        }
+        if source.is_empty() {
+            self.aabbs.remove(&pos);
+        } else {
+            self.aabbs.insert(pos, source);
+        }
+    }
+
+    /// Halo blocks with collision boxes
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &SmallVec<[Aabb3D; 1]>)> {
+        self.aabbs.iter().map(|(pos, block)| (*pos, block))
+    }
+
+    pub fn is_full_block(&self, pos: IVec3) -> bool {
+        self.aabbs
+            .get(&pos)
+            .is_some_and(|block| block.len() == 1 && block[0] == Aabb3D::FULL_BLOCK)
+    }
+
+    /// Collision boxes in the block at `pos`, including shapes taller than a block from the block below
+    pub fn iter_block(&self, pos: IVec3) -> impl Iterator<Item = (IVec3, &Aabb3D)> {
+        let below = pos - IVec3::Y;
+        self.aabbs
+            .get(&pos)
+            .into_iter()
+            .flatten()
+            .map(move |aabb| (pos, aabb))
+            .chain(
+                self.aabbs
+                    .get(&below)
+                    .into_iter()
+                    .flatten()
+                    .filter(|aabb| aabb.max_y() > 1.0)
+                    .map(move |aabb| (below, aabb)),
+            )
+    }
+}
+
+#[derive(Clone)]
 pub struct SubChunk {
     pub location: IVec3,
     aabbs: Vec<(UVec3, Aabb3D)>,
     blocks: Box<[[[SmallVec<[usize; 1]>; SUB_CHUNK_HEIGHT]; CHUNK_WIDTH]; CHUNK_WIDTH]>,
+    halo: SubChunkHalo,
+    /// Full blocks merged into larger boxes by `apply_greedy_meshing`
+    merged_full_blocks: Vec<(UVec3, Aabb3D)>,
     block_collision_mask: Box<[[u16; CHUNK_WIDTH]; CHUNK_WIDTH]>,
     block_floor_mask: Box<[[u16; CHUNK_WIDTH]; CHUNK_WIDTH]>,
+    block_ceiling_mask: Box<[[u16; CHUNK_WIDTH]; CHUNK_WIDTH]>,
     full_block_mask: Box<[[u16; CHUNK_WIDTH]; CHUNK_WIDTH]>,
 }
 
 impl SubChunk {
-    pub fn new(
-        location: IVec3,
-        source: Box<[[[SmallVec<[Aabb3D; 1]>; SUB_CHUNK_HEIGHT]; CHUNK_WIDTH]; CHUNK_WIDTH]>,
-    ) -> Self {
+    pub fn new(location: IVec3, source: SubChunkSource) -> Self {
+        Self::new_with_halo(location, source, SubChunkHalo::default())
+    }
+
+    /// Build a sub chunk using the surrounding blocks for tall collisions, occlusion and headroom
+    pub fn new_with_halo(location: IVec3, source: SubChunkSource, halo: SubChunkHalo) -> Self {
         let mut aabbs = vec![];
         let mut blocks: Box<
             [[[SmallVec<[usize; 1]>; SUB_CHUNK_HEIGHT]; CHUNK_WIDTH]; CHUNK_WIDTH],
@@ -103,11 +625,12 @@ impl SubChunk {
                     if !block.is_empty() {
                         column_collision_blocks |= 1 << y;
                         if block[0] == Aabb3D::FULL_BLOCK && block.len() == 1 {
-                            column_full_blocks = 1 << y;
+                            column_full_blocks |= 1 << y;
                         }
                         for aabb in block.into_iter() {
                             if aabb.max_y() > 1.0 {
-                                column_collision_blocks |= 2 << y; // TODO: Handle chunk boundaries
+                                // Overflow out of the top of the sub chunk is picked up by the halo of the one above
+                                column_collision_blocks |= 2 << y;
                                 blocks[z][x]
                                     .get_mut(y + 1)
                                     .and_then(|a| Some(a.push(aabbs.len())));
@@ -133,14 +656,52 @@ impl SubChunk {
             location,
             aabbs,
             blocks,
+            halo,
+            merged_full_blocks: vec![],
             block_floor_mask: collision_blocks.clone(),
+            block_ceiling_mask: collision_blocks.clone(),
             block_collision_mask: collision_blocks,
             full_block_mask: full_blocks,
         };
         chunk.apply_full_block_occlusion();
+        chunk.apply_greedy_meshing();
         chunk
     }
 
+    pub fn contains(pos: IVec3) -> bool {
+        pos.cmpge(IVec3::ZERO).all() && pos.cmplt(SUB_CHUNK_SIZE).all()
+    }
+
+    /// Collision boxes of each block, in the form taken by `SubChunk::new`
+    pub fn source(&self) -> SubChunkSource {
+        let mut source: SubChunkSource = Default::default();
+        for (pos, aabb) in self.aabbs.iter() {
+            source[pos.z as usize][pos.x as usize][pos.y as usize].push(aabb.clone());
+        }
+        source
+    }
+
+    pub fn halo(&self) -> &SubChunkHalo {
+        &self.halo
+    }
+
+    /// Collision boxes in the block at `pos` relative to the sub chunk origin, using the halo
+    /// outside of the sub chunk. Includes shapes taller than a block from the block below.
+    pub fn iter_block(&self, pos: IVec3) -> impl Iterator<Item = (IVec3, &Aabb3D)> {
+        let interior: &[usize] = if Self::contains(pos) {
+            &self.blocks[pos.z as usize][pos.x as usize][pos.y as usize]
+        } else {
+            &[]
+        };
+        interior
+            .iter()
+            .map(|index| {
+                let (pos, aabb) = &self.aabbs[*index];
+                (pos.as_ivec3(), aabb)
+            })
+            .chain(self.halo.iter_block(pos))
+    }
+
     pub fn iter_floor(&self) -> impl Iterator<Item = (UVec3, &Aabb3D)> {
         self.aabbs.iter().flat_map(|(pos, aabb)| {
             let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
@@ -153,45 +714,298 @@ impl SubChunk {
     }
 
     pub fn iter_ceiling(&self) -> impl Iterator<Item = (UVec3, &Aabb3D)> {
-        // TODO: ADD MASKS
-        self.iter_collisions()
+        self.aabbs.iter().flat_map(|(pos, aabb)| {
+            let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+            if self.block_ceiling_mask[z][x] >> y & 1 == 1 {
+                Some((*pos, aabb))
+            } else {
+                None
+            }
+        })
     }
 
+    /// Collision boxes with full blocks merged together, see `apply_greedy_meshing`
     pub fn iter_collisions(&self) -> impl Iterator<Item = (UVec3, &Aabb3D)> {
-        self.aabbs.iter().map(|(pos, aabb)| (*pos, aabb))
+        self.aabbs
+            .iter()
+            .filter(|(pos, _)| {
+                self.full_block_mask[pos.z as usize][pos.x as usize] >> pos.y & 1 == 0
+            })
+            .chain(self.merged_full_blocks.iter())
+            .map(|(pos, aabb)| (*pos, aabb))
     }
 
-    pub fn build_nav_mesh(&self) -> SubChunkNavMesh {
-        let mut ceiling: Vec<NavMeshLayer> = vec![];
+    /// Floor layers with overlapping nodes removed, before they are cut by obstacles above
+    fn floor_layers(&self, agent: &AgentParams) -> Vec<NavMeshLayer> {
         let mut floor: Vec<NavMeshLayer> = vec![];
-
         for (pos, aabb) in self.iter_floor() {
-            Self::insert_aabb_into_layers(&mut floor, aabb, pos, NavMeshLayerType::Floor);
+            Self::insert_aabb_into_layers(&mut floor, aabb, pos, NavMeshLayerType::Floor, agent);
         }
+        self.remove_overlap_floor(&mut floor);
+        floor
+    }
+
+    /// Blocks outside of the halo are treated as empty, so the halo should be at least
+    /// `SubChunkHalo::bounds(agent)` for the surroundings of the sub chunk to be accounted for.
+    pub fn build_nav_mesh(&self, agent: &AgentParams) -> SubChunkNavMesh {
+        let mut ceiling: Vec<NavMeshLayer> = vec![];
+        let mut floor = self.floor_layers(agent);
+
         for (pos, aabb) in self.iter_ceiling() {
-            Self::insert_aabb_into_layers(&mut ceiling, aabb, pos, NavMeshLayerType::Ceiling);
+            Self::insert_aabb_into_layers(
+                &mut ceiling,
+                aabb,
+                pos,
+                NavMeshLayerType::Ceiling,
+                agent,
+            );
         }
 
-        self.remove_overlap_floor(&mut floor);
-        self.cut_floor(&mut floor);
+        self.cut_floor(&mut floor, agent);
+
+        for layer in floor.iter_mut() {
+            layer.merge_nodes();
+            layer.link_adjacent();
+        }
+        self.link_layers(&mut floor, agent);
 
         SubChunkNavMesh {
             location: self.location,
+            agent: agent.clone(),
             floor: floor.into(),
             ceiling: ceiling.into(),
         }
     }
 
+    /// Check that no collisions are within reach of an agent centred in `region` between the heights
+    /// `min_y` and `max_y`, relative to the sub chunk
+    pub fn is_clear(&self, region: &Aabb2D, min_y: f32, max_y: f32, agent: &AgentParams) -> bool {
+        let reach = region.inflate(Vec2::splat(agent.radius));
+        let volume = Aabb3D([
+            reach.min_x,
+            min_y,
+            reach.min_y,
+            reach.max_x,
+            max_y,
+            reach.max_y,
+        ]);
+        for z in reach.min_y.floor() as i32..reach.max_y.ceil() as i32 {
+            for x in reach.min_x.floor() as i32..reach.max_x.ceil() as i32 {
+                for y in min_y.floor() as i32..max_y.ceil() as i32 {
+                    for (pos, aabb) in self.iter_block(IVec3 { x, y, z }) {
+                        if volume.overlaps(&aabb.translate(pos.as_vec3())) {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Generate step, jump and drop links between layers, and jump links across gaps
+    fn link_layers(&self, floor: &mut [NavMeshLayer], agent: &AgentParams) {
+        let mut links: Vec<(NavNodeId, NavMeshLink)> = vec![];
+
+        for (from_layer, from) in floor.iter().enumerate() {
+            for (to_layer, to) in floor.iter().enumerate() {
+                let delta = to.height - from.height;
+                if delta > agent.jump_height || -delta > agent.max_drop {
+                    continue;
+                }
+
+                for (from_node, from_aabb) in
+                    from.nodes.iter().map(|node| node.layer_aabb()).enumerate()
+                {
+                    let reach = from_aabb.inflate(Vec2::splat(agent.max_gap));
+                    for to_node in to.nodes_near(&reach) {
+                        let link = self.link_nodes(
+                            &from_aabb,
+                            from.height,
+                            &to.nodes[to_node].layer_aabb(),
+                            to.height,
+                            from_layer == to_layer,
+                            agent,
+                        );
+
+                        if let Some((link_type, cost, axis, value, min, max)) = link {
+                            links.push((
+                                NavNodeId {
+                                    layer: from_layer,
+                                    node: from_node,
+                                },
+                                NavMeshLink {
+                                    to: NavNodeId {
+                                        layer: to_layer,
+                                        node: to_node,
+                                    },
+                                    link_type,
+                                    cost,
+                                    axis,
+                                    value,
+                                    min,
+                                    max,
+                                },
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (from, link) in links.into_iter() {
+            floor[from.layer].nodes[from.node].links.push(link);
+        }
+    }
+
+    pub(super) fn link_nodes(
+        &self,
+        from: &Aabb2D,
+        from_height: f32,
+        to: &Aabb2D,
+        to_height: f32,
+        same_layer: bool,
+        agent: &AgentParams,
+    ) -> Option<(NavMeshLinkType, f32, u8, f32, f32, f32)> {
+        const EPSILON: f32 = 1e-4;
+
+        let from_array = from.to_array();
+        let to_array = to.to_array();
+        let delta = to_height - from_height;
+        let costs = &agent.link_costs;
+
+        let overlap = [0, 1].map(|axis| {
+            from_array[1][axis].min(to_array[1][axis]) - from_array[0][axis].max(to_array[0][axis])
+        });
+
+        // Crossing axis is the one with the least overlap, or the one with a gap
+        let axis = if overlap[0] <= overlap[1] { 0 } else { 1 };
+        let edge_axis = 1 - axis;
+        if overlap[edge_axis] <= EPSILON {
+            return None;
+        }
+
+        let mut value = (from_array[0][axis].max(to_array[0][axis])
+            + from_array[1][axis].min(to_array[1][axis]))
+            * 0.5;
+        let min = from_array[0][edge_axis].max(to_array[0][edge_axis]);
+        let max = from_array[1][edge_axis].min(to_array[1][edge_axis]);
+        let dir = (from_array[0][axis] + from_array[1][axis]
+            < to_array[0][axis] + to_array[1][axis]) as u8;
+        let edge = axis as u8 + 2 * dir;
+
+        // Region crossed by the centre of the agent
+        let mut crossing = [[0.0; 2]; 2];
+        crossing[0][edge_axis] = min;
+        crossing[1][edge_axis] = max;
+        crossing[0][axis] = from_array[1][axis].min(to_array[1][axis]);
+        crossing[1][axis] = from_array[0][axis].max(to_array[0][axis]);
+        if crossing[0][axis] > crossing[1][axis] {
+            let swap = crossing[0][axis];
+            crossing[0][axis] = crossing[1][axis];
+            crossing[1][axis] = swap;
+        }
+
+        if overlap[axis] >= -EPSILON {
+            if same_layer {
+                // Walkable, handled by adjacency
+                return None;
+            }
+
+            if overlap[axis] > EPSILON {
+                // The upper node covers part of the lower one, cross the edge of the upper node
+                // onto the part of the lower node beyond it instead of going through its floor
+                let (upper, lower, side) = if delta < 0.0 {
+                    (&from_array, &to_array, dir as usize)
+                } else {
+                    (&to_array, &from_array, 1 - dir as usize)
+                };
+                let beyond = if side == 1 {
+                    lower[1][axis] > upper[1][axis] + EPSILON
+                } else {
+                    lower[0][axis] < upper[0][axis] - EPSILON
+                };
+                if !beyond {
+                    return None;
+                }
+                value = upper[side][axis];
+                crossing[0][axis] = value;
+                crossing[1][axis] = value;
+            }
+
+            if !self.is_clear(
+                &crossing.into(),
+                from_height.min(to_height),
+                from_height.max(to_height) + agent.height,
+                agent,
+            ) {
+                return None;
+            }
+
+            let (link_type, cost) = if delta < 0.0 {
+                (NavMeshLinkType::Drop, costs.drop * -delta)
+            } else if delta <= agent.step_height {
+                (NavMeshLinkType::StepUp, costs.step)
+            } else {
+                (NavMeshLinkType::JumpUp, costs.jump)
+            };
+            return Some((link_type, cost, edge, value, min, max));
+        }
+
+        let gap = -overlap[axis];
+        if delta > 0.0 || gap > agent.max_gap {
+            return None;
+        }
+        if !self.is_clear(
+            &crossing.into(),
+            to_height,
+            from_height + agent.height + agent.jump_height,
+            agent,
+        ) {
+            return None;
+        }
+
+        Some((
+            NavMeshLinkType::GapJump,
+            costs.jump + costs.gap * gap + costs.drop * -delta,
+            edge,
+            value,
+            min,
+            max,
+        ))
+    }
+
+    /// Remove floors and ceilings hidden by an adjacent full block
     fn apply_full_block_occlusion(&mut self) {
         for z in 0..CHUNK_WIDTH {
             for x in 0..CHUNK_WIDTH {
-                self.block_floor_mask[z][x] = !(self.full_block_mask[z][x] >> 1)
-                    & !(self.full_block_mask[z][x] >> 2)
-                    & self.block_collision_mask[z][x];
+                // Extend column with the halo block above the sub chunk
+                let halo_above = self.halo.is_full_block(IVec3 {
+                    x: x as i32,
+                    y: SUB_CHUNK_HEIGHT as i32,
+                    z: z as i32,
+                }) as u32;
+                let column = self.full_block_mask[z][x] as u32 | halo_above << SUB_CHUNK_HEIGHT;
+
+                // Only the block directly above occludes the floor for every agent, headroom
+                // is handled when cutting the floor
+                self.block_floor_mask[z][x] =
+                    !(column >> 1) as u16 & self.block_collision_mask[z][x];
+
+                // Ceilings are occluded by the full block directly below
+                let halo_below = self.halo.is_full_block(IVec3 {
+                    x: x as i32,
+                    y: -1,
+                    z: z as i32,
+                }) as u32;
+                let column = (self.full_block_mask[z][x] as u32) << 1 | halo_below;
+
+                self.block_ceiling_mask[z][x] = !column as u16 & self.block_collision_mask[z][x];
             }
         }
     }
 
+    /// Merge full blocks into a smaller set of boxes covering the same volume
     pub fn apply_greedy_meshing(&mut self) {
         fn check_mask(mask: &[[u16; CHUNK_WIDTH]; CHUNK_WIDTH], pos: &[usize; 3]) -> bool {
             return mask[pos[2]][pos[0]] & (1 << pos[1]) != 0;
@@ -200,7 +1014,10 @@ impl SubChunk {
             mask[pos[2]][pos[0]] |= (value as u16) << pos[1];
         }
 
+        const SIZE: [usize; 3] = [CHUNK_WIDTH, SUB_CHUNK_HEIGHT, CHUNK_WIDTH];
+
         let mut visited = Box::new([[0u16; CHUNK_WIDTH]; CHUNK_WIDTH]);
+        let mut merged = vec![];
 
         // First apply to full blocks
         for start_index in 0..CHUNK_WIDTH * CHUNK_WIDTH * SUB_CHUNK_HEIGHT {
@@ -217,6 +1034,7 @@ impl SubChunk {
             if check_mask(&visited, &start) || !check_mask(&self.full_block_mask, &start) {
                 continue;
             }
+            set_mask(&mut visited, &start, true);
 
             // End point exclusive
             let mut end = [start_x + 1, start_y + 1, start_z + 1];
@@ -226,28 +1044,26 @@ impl SubChunk {
                 let u_axis: usize = (axis + 1).rem_euclid(3);
                 let v_axis: usize = (axis + 2).rem_euclid(3);
 
-                loop {
+                while end[axis] < SIZE[axis] {
                     let mut valid = true;
                     'check_expansion: for u in start[u_axis]..end[u_axis] {
-                        for v in start[u_axis]..end[u_axis] {
+                        for v in start[v_axis]..end[v_axis] {
                             let mut pos = end.clone();
                             pos[u_axis] = u;
                             pos[v_axis] = v;
 
-                            if check_mask(&visited, &start)
-                                || !check_mask(&self.full_block_mask, &start)
+                            if check_mask(&visited, &pos)
+                                || !check_mask(&self.full_block_mask, &pos)
                             {
                                 valid = false;
                                 break 'check_expansion;
-                            } else {
-                                set_mask(&mut visited, &pos, true);
                             }
                         }
                     }
                     if valid {
                         // Update visited mask with new blocks
                         for u in start[u_axis]..end[u_axis] {
-                            for v in start[u_axis]..end[u_axis] {
+                            for v in start[v_axis]..end[v_axis] {
                                 let mut pos = end.clone();
                                 pos[u_axis] = u;
                                 pos[v_axis] = v;
@@ -260,66 +1076,69 @@ impl SubChunk {
                     }
                 }
             }
-            dbg!(start, end);
+
+            merged.push((
+                UVec3 {
+                    x: start_x as u32,
+                    y: start_y as u32,
+                    z: start_z as u32,
+                },
+                Aabb3D([
+                    0.0,
+                    0.0,
+                    0.0,
+                    (end[0] - start_x) as f32,
+                    (end[1] - start_y) as f32,
+                    (end[2] - start_z) as f32,
+                ]),
+            ));
         }
+
+        self.merged_full_blocks = merged;
     }
 
+    /// Replace the inflated floor nodes of each layer with disjoint nodes covering the same area
+    ///
+    /// Larger nodes are kept whole, smaller nodes are cut down to the area not already covered.
     fn remove_overlap_floor(&self, floor: &mut Vec<NavMeshLayer>) {
-        // remove needless overlap for inflated full blocks first
-        // doesn't work
-
-        // for layer in floor {
-        //     let mut updated_aabbs = vec![];
-        //     for node in layer.nodes.iter() {
-        //         if node.aabb
-        //             == (Aabb2D {
-        //                 min_x: 0.3,
-        //                 max_x: 1.3,
-        //                 min_y: 0.3,
-        //                 max_y: 1.3,
-        //             })
-        //         {
-        //             let mut updated_aabb = node.aabb.clone();
-        //             let mut self_array = updated_aabb.to_array();
-
-        //             for dir in 0..=1 {
-        //                 for axis in 0..=1 {
-        //                     let target_x = ((1 - axis) * (2 * dir - 1)) as usize;
-        //                     let target_z = (axis * (2 * dir - 1)) as usize;
-
-        //                     for aabb in layer.blocks[target_z][target_x].clone() {
-        //                         let other_array = &layer.nodes[aabb].aabb.to_array();
-
-        //                         if (dir == 0 && other_array[1][axis] >= 1.0)
-        //                             || (dir == 1 && other_array[0][axis] <= 0.0)
-        //                         {
-        //                             if other_array[0][1 - axis] == self_array[0][1 - axis]
-        //                                 && other_array[1][1 - axis] == self_array[1][1 - axis]
-        //                             {
-        //                                 self_array[dir][axis] =
-        //                                     self_array[dir][axis].clamp(0.0, 1.0);
-        //                                 updated_aabb = self_array.into();
-        //                             }
-        //                         }
-        //                     }
-        //                 }
-        //             }
-        //             updated_aabbs.push(updated_aabb);
-        //         }
-        //     }
-        //     for (node, aabb) in layer.nodes.iter_mut().zip(updated_aabbs.into_iter()) {
-        //         node.aabb = aabb;
-        //     }
-        // }
-
-        // reduce other sources of overlapping
-    }
-
-    fn cut_floor(&self, floor: &mut Vec<NavMeshLayer>) {
+        for layer in floor.iter_mut() {
+            for block in layer.blocks.iter_mut().flatten() {
+                block.clear();
+            }
+
+            let mut nodes: Vec<NavMeshNode> = layer.nodes.drain(0..layer.nodes.len()).collect();
+            nodes.sort_by(|a, b| b.aabb.area().total_cmp(&a.aabb.area()));
+
+            for node in nodes {
+                let mut pieces = vec![node.aabb.clone()];
+                for other in layer.nodes_near(&node.layer_aabb()) {
+                    let other = &layer.nodes[other];
+                    let offset = other.pos.as_ivec2() - node.pos.as_ivec2();
+                    let other_aabb = other.aabb.translate(offset.as_vec2());
+
+                    pieces = pieces
+                        .into_iter()
+                        .flat_map(|piece| piece.subtract(&other_aabb))
+                        .filter(|piece| !piece.is_sliver())
+                        .collect();
+                    if pieces.is_empty() {
+                        break;
+                    }
+                }
+                for piece in pieces {
+                    layer.insert(piece, node.pos);
+                }
+            }
+        }
+    }
+
+    /// Cut the parts of floor nodes without head room for the agent out of each layer
+    fn cut_floor(&self, floor: &mut Vec<NavMeshLayer>, agent: &AgentParams) {
         for layer in floor.iter_mut() {
             let height = layer.height;
-            let cut_indices = (height.max(0.0) as usize)
-                ..(((height + 1.8).ceil() + 0.1) as usize).min(SUB_CHUNK_HEIGHT - 1);
+            let cut_indices = (height.floor() as i32)..((height + agent.height).ceil() as i32);
+            // Inflated obstacles overlap nodes up to this many blocks away
+            let reach = (2.0 * agent.radius).ceil() as i32;
 
             let mut new_nodes: Vec<(UVec2, Aabb2D)> = vec![];
             for block in layer.blocks.iter_mut().flatten() {
@@ -327,37 +1146,37 @@ impl SubChunk {
             }
 
             for node in layer.nodes.drain(0..layer.nodes.len()) {
+                let node_pos = node.pos.as_ivec2();
                 let mut cutting_stack = vec![node.aabb.clone()];
                 'next_aabb: while let Some(aabb) = cutting_stack.pop() {
                     for cut_layer in cut_indices.clone() {
-                        for sample_z in (node.pos.y as isize - 1).max(0isize)
-                            ..=(node.pos.y as isize + 1).min(CHUNK_WIDTH as isize - 1)
-                        {
-                            for sample_x in (node.pos.x as isize - 1).max(0isize)
-                                ..=(node.pos.x as isize + 1).min(CHUNK_WIDTH as isize - 1)
-                            {
-                                for (cutting_aabb_pos, cutting_aabb) in self.blocks
-                                    [sample_z as usize][sample_x as usize][cut_layer]
-                                    .iter()
-                                    .map(|index| &self.aabbs[*index])
-                                {
+                        for sample_z in node_pos.y - reach..=node_pos.y + reach {
+                            for sample_x in node_pos.x - reach..=node_pos.x + reach {
+                                for (cutting_aabb_pos, cutting_aabb) in self.iter_block(IVec3 {
+                                    x: sample_x,
+                                    y: cut_layer,
+                                    z: sample_z,
+                                }) {
                                     let cutting_aabb_offset = IVec2 {
-                                        x: cutting_aabb_pos.x as i32,
-                                        y: cutting_aabb_pos.z as i32,
-                                    } - node.pos.as_ivec2();
+                                        x: cutting_aabb_pos.x,
+                                        y: cutting_aabb_pos.z,
+                                    } - node_pos;
+                                    let cutting_aabb_y = cutting_aabb_pos.y as f32;
 
-                                    if cut_layer as f32 + cutting_aabb.min_y() - 1.8 < height
-                                        && height < cut_layer as f32 + cutting_aabb.max_y()
+                                    if cutting_aabb_y + cutting_aabb.min_y() - agent.height < height
+                                        && height < cutting_aabb_y + cutting_aabb.max_y()
                                     {
                                         let cut = aabb.subtract(
                                             &cutting_aabb
                                                 .surface_projection(1)
                                                 .translate(cutting_aabb_offset.as_vec2())
-                                                .inflate(Vec2::splat(0.3)),
+                                                .inflate(Vec2::splat(agent.radius)),
                                         );
 
                                         if cut.len() > 1 || Some(&aabb) != cut.first() {
-                                            cutting_stack.extend(cut.into_iter());
+                                            cutting_stack.extend(
+                                                cut.into_iter().filter(|piece| !piece.is_sliver()),
+                                            );
                                             continue 'next_aabb;
                                         }
                                     }
@@ -376,19 +1195,41 @@ impl SubChunk {
             }
 
             for (pos, aabb) in new_nodes.into_iter() {
-                layer.blocks[pos.y as usize][pos.x as usize].push(layer.nodes.len());
-                layer.nodes.push(NavMeshNode {
-                    aabb,
-                    pos,
-                    _adjacent: smallvec![],
-                });
+                layer.insert(aabb, pos);
             }
         }
     }
 
     /// Occlude self using another sub chunk
-    fn _apply_other_occlusion(&mut self, _other: &Self) {
-        todo!();
+    ///
+    /// Blocks of `other` bordering this sub chunk are copied into the halo, so they are used for
+    /// floor occlusion and cutting. Sub chunks that aren't neighbours are ignored.
+    pub fn apply_other_occlusion(&mut self, other: &Self) {
+        let delta = other.location - self.location;
+        if delta == IVec3::ZERO || delta.abs().max_element() > 1 {
+            return;
+        }
+        let offset = delta * SUB_CHUNK_SIZE;
+
+        let positions: Vec<IVec3> = self.halo.positions().collect();
+        for pos in positions {
+            let other_pos = pos - offset;
+            if !Self::contains(other_pos) {
+                continue;
+            }
+            let other_pos = other_pos.as_uvec3();
+
+            let source = other.blocks[other_pos.z as usize][other_pos.x as usize]
+                [other_pos.y as usize]
+                .iter()
+                .map(|index| &other.aabbs[*index])
+                .filter(|(pos, _)| *pos == other_pos)
+                .map(|(_, aabb)| aabb.clone())
+                .collect();
+            self.halo.insert(pos, source);
+        }
+
+        self.apply_full_block_occlusion();
     }
 
     fn insert_aabb_into_layers(
@@ -396,6 +1237,7 @@ impl SubChunk {
         aabb: &Aabb3D,
         block_location: UVec3,
         layer_type: NavMeshLayerType,
+        agent: &AgentParams,
     ) {
         let height = block_location.y as f32
             + match layer_type {
@@ -403,7 +1245,9 @@ impl SubChunk {
                 NavMeshLayerType::Floor => aabb.max_y(),
             };
 
-        let surface = aabb.surface_projection(1).inflate(Vec2::splat(0.3));
+        let surface = aabb
+            .surface_projection(1)
+            .inflate(Vec2::splat(agent.radius));
 
         // TODO: Optimise by using initial bounds for search (can't change by more that 1.5 blocks)
         match layers.binary_search_by(|layer| layer.height.partial_cmp(&height).unwrap()) {
@@ -432,3 +1276,16 @@ impl SubChunk {
         }
     }
 }
+
+/// Stages of `SubChunk::build_nav_mesh` which are benchmarked on their own, not part of the API
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+impl SubChunk {
+    pub fn bench_floor_layers(&self, agent: &AgentParams) -> Vec<NavMeshLayer> {
+        self.floor_layers(agent)
+    }
+
+    pub fn bench_cut_floor(&self, floor: &mut Vec<NavMeshLayer>, agent: &AgentParams) {
+        self.cut_floor(floor, agent)
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+use bevy::math::Vec3;
+
+use super::aabb_2d::Aabb2D;
+use super::optimise_world::{
+    NavMeshAdjacent, NavMeshLayer, NavMeshLink, NavMeshLinkType, NavNodeId, SubChunkNavMesh,
+};
+
+/// Edge crossed when moving between two nodes, in world coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portal {
+    pub min: Vec3,
+    pub max: Vec3,
+    /// Movement needed to cross the portal, `None` when walking
+    pub link_type: Option<NavMeshLinkType>,
+}
+
+impl Portal {
+    pub fn midpoint(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Portal between a node and one of its adjacent nodes in the same layer
+    pub fn between(
+        layer: &NavMeshLayer,
+        from: usize,
+        adjacent: &NavMeshAdjacent,
+        origin: Vec3,
+    ) -> Self {
+        Self::crossing(
+            &layer.nodes[from].layer_aabb(),
+            &layer.nodes[adjacent.index()].layer_aabb(),
+            adjacent,
+            layer.height,
+            origin,
+        )
+    }
+
+    /// Portal between two adjacent areas at the same height, relative to `origin`
+    pub fn crossing(
+        a: &Aabb2D,
+        b: &Aabb2D,
+        adjacent: &NavMeshAdjacent,
+        height: f32,
+        origin: Vec3,
+    ) -> Self {
+        let a = a.to_array();
+        let b = b.to_array();
+
+        let axis = adjacent.axis() % 2;
+        let value = (a[0][axis as usize].max(b[0][axis as usize])
+            + a[1][axis as usize].min(b[1][axis as usize]))
+            * 0.5;
+        let (min, max) = adjacent.interval();
+
+        Self::from_edge(axis, value, min, max, height, origin, None)
+    }
+
+    /// Portal crossed by a link, at the height of the destination layer
+    pub fn from_link(link: &NavMeshLink, height: f32, origin: Vec3) -> Self {
+        Self::from_edge(
+            link.axis % 2,
+            link.value,
+            link.min,
+            link.max,
+            height,
+            origin,
+            Some(link.link_type),
+        )
+    }
+
+    fn from_edge(
+        axis: u8,
+        value: f32,
+        min: f32,
+        max: f32,
+        height: f32,
+        origin: Vec3,
+        link_type: Option<NavMeshLinkType>,
+    ) -> Self {
+        let point = |edge_value: f32| {
+            let (x, z) = if axis == 0 {
+                (value, edge_value)
+            } else {
+                (edge_value, value)
+            };
+            origin + Vec3 { x, y: height, z }
+        };
+
+        Self {
+            min: point(min),
+            max: point(max),
+            link_type,
+        }
+    }
+}
+
+/// Connection from a node to one of its successors
+#[derive(Debug, Clone)]
+pub struct NavEdge<N> {
+    pub node: N,
+    pub portal: Portal,
+    /// Cost in addition to the distance travelled
+    pub cost: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct NavPath<N> {
+    pub nodes: Vec<N>,
+    /// Portals between consecutive nodes, `portals[i]` connects `nodes[i]` to `nodes[i + 1]`
+    pub portals: Vec<Portal>,
+    pub cost: f32,
+}
+
+/// Graph of walkable nodes which can be searched by `find_path`
+pub trait NavGraph {
+    type Node: Copy + Eq + Hash;
+
+    /// Node the world position `pos` is standing on
+    fn node_at(&self, pos: Vec3) -> Option<Self::Node>;
+
+    /// World height of the floor of `node`
+    fn height(&self, node: Self::Node) -> f32;
+
+    /// Nodes reachable from `node`
+    fn successors(&self, node: Self::Node) -> Vec<NavEdge<Self::Node>>;
+}
+
+impl NavGraph for SubChunkNavMesh {
+    type Node = NavNodeId;
+
+    fn node_at(&self, pos: Vec3) -> Option<NavNodeId> {
+        SubChunkNavMesh::node_at(self, pos)
+    }
+
+    fn height(&self, node: NavNodeId) -> f32 {
+        self.origin().y + self.floor[node.layer].height
+    }
+
+    fn successors(&self, node: NavNodeId) -> Vec<NavEdge<NavNodeId>> {
+        let layer = &self.floor[node.layer];
+        let origin = self.origin();
+        let from = &layer.nodes[node.node];
+
+        let walk = from.adjacent.iter().map(|adjacent| NavEdge {
+            node: NavNodeId {
+                layer: node.layer,
+                node: adjacent.index(),
+            },
+            portal: Portal::between(layer, node.node, adjacent, origin),
+            cost: 0.0,
+        });
+        let links = from.links.iter().map(|link| NavEdge {
+            node: link.to,
+            portal: Portal::from_link(link, self.floor[link.to.layer].height, origin),
+            cost: link.cost,
+        });
+
+        walk.chain(links).collect()
+    }
+}
+
+struct OpenNode<N> {
+    estimate: f32,
+    cost: f32,
+    node: N,
+}
+
+impl<N> PartialEq for OpenNode<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl<N> Eq for OpenNode<N> {}
+
+impl<N> PartialOrd for OpenNode<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for OpenNode<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the lowest estimate first
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+struct Visit<N> {
+    cost: f32,
+    entry: Vec3,
+    previous: Option<(N, Portal)>,
+}
+
+/// A* search between two world positions, crossing portals at their midpoints
+///
+/// The cost of a node is the distance walked across its area, from the portal it is entered
+/// through to the one it is left through, with portals at the height of the layer they lead to.
+/// Links add their cost on top, which for drops scales with the height between the layers.
+pub fn find_path<G: NavGraph>(graph: &G, start: Vec3, goal: Vec3) -> Option<NavPath<G::Node>> {
+    let start_node = graph.node_at(start)?;
+    let goal_node = graph.node_at(goal)?;
+
+    let start = Vec3 {
+        y: graph.height(start_node),
+        ..start
+    };
+    let goal = Vec3 {
+        y: graph.height(goal_node),
+        ..goal
+    };
+
+    let mut visited: HashMap<G::Node, Visit<G::Node>> = HashMap::new();
+    let mut open = BinaryHeap::new();
+
+    visited.insert(
+        start_node,
+        Visit {
+            cost: 0.0,
+            entry: start,
+            previous: None,
+        },
+    );
+    open.push(OpenNode {
+        estimate: start.distance(goal),
+        cost: 0.0,
+        node: start_node,
+    });
+
+    while let Some(OpenNode { cost, node, .. }) = open.pop() {
+        let entry = {
+            let visit = &visited[&node];
+            if cost > visit.cost {
+                // Already reached with a lower cost
+                continue;
+            }
+            visit.entry
+        };
+
+        if node == goal_node {
+            let mut nodes = vec![node];
+            let mut portals = vec![];
+            let mut current = node;
+            while let Some((previous, portal)) = &visited[&current].previous {
+                nodes.push(*previous);
+                portals.push(portal.clone());
+                current = *previous;
+            }
+            nodes.reverse();
+            portals.reverse();
+
+            return Some(NavPath {
+                nodes,
+                portals,
+                cost: cost + entry.distance(goal),
+            });
+        }
+
+        for NavEdge {
+            node: next,
+            portal,
+            cost: edge_cost,
+        } in graph.successors(node)
+        {
+            let next_entry = portal.midpoint();
+            let next_cost = cost + entry.distance(next_entry) + edge_cost;
+
+            if visited
+                .get(&next)
+                .is_none_or(|visit| next_cost < visit.cost)
+            {
+                visited.insert(
+                    next,
+                    Visit {
+                        cost: next_cost,
+                        entry: next_entry,
+                        previous: Some((node, portal)),
+                    },
+                );
+                open.push(OpenNode {
+                    estimate: next_cost + next_entry.distance(goal),
+                    cost: next_cost,
+                    node: next,
+                });
+            }
+        }
+    }
+
+    None
+}
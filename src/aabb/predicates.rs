@@ -0,0 +1,113 @@
+use super::aabb_2d::Aabb2D;
+use super::aabb_3d::Aabb3D;
+
+/// Default tolerance used when comparing box boundaries
+pub const EPSILON: f32 = 1e-4;
+
+/// Axis aligned box with an interval along each axis
+pub trait Bounds {
+    const AXES: usize;
+
+    fn min(&self, axis: usize) -> f32;
+    fn max(&self, axis: usize) -> f32;
+}
+
+impl Bounds for Aabb2D {
+    const AXES: usize = 2;
+
+    fn min(&self, axis: usize) -> f32 {
+        match axis {
+            0 => self.min_x,
+            1 => self.min_y,
+            _ => unreachable!(),
+        }
+    }
+    fn max(&self, axis: usize) -> f32 {
+        match axis {
+            0 => self.max_x,
+            1 => self.max_y,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Bounds for Aabb3D {
+    const AXES: usize = 3;
+
+    fn min(&self, axis: usize) -> f32 {
+        self.0[axis]
+    }
+    fn max(&self, axis: usize) -> f32 {
+        self.0[axis + 3]
+    }
+}
+
+/// Length of the overlap between two intervals, negative when there is a gap between them
+pub fn interval_overlap(a_min: f32, a_max: f32, b_min: f32, b_max: f32) -> f32 {
+    a_max.min(b_max) - a_min.max(b_min)
+}
+
+/// Number of axes along which `a` and `b` only touch, or `None` if there's a gap between them
+fn touching_axes<B: Bounds>(a: &B, b: &B, epsilon: f32) -> Option<usize> {
+    let mut touching = 0;
+    for axis in 0..B::AXES {
+        let overlap = interval_overlap(a.min(axis), a.max(axis), b.min(axis), b.max(axis));
+        if overlap < -epsilon {
+            return None;
+        }
+        if overlap <= epsilon {
+            touching += 1;
+        }
+    }
+    Some(touching)
+}
+
+/// Boxes share some volume (area in 2D), overlapping by more than `epsilon` along every axis
+pub fn overlaps<B: Bounds>(a: &B, b: &B, epsilon: f32) -> bool {
+    touching_axes(a, b, epsilon) == Some(0)
+}
+
+/// Boxes meet along an edge in 2D, or a face in 3D, without overlapping
+pub fn touching_edge<B: Bounds>(a: &B, b: &B, epsilon: f32) -> bool {
+    touching_axes(a, b, epsilon) == Some(1)
+}
+
+/// Boxes only meet at a corner, or along an edge in 3D
+pub fn touching_corner<B: Bounds>(a: &B, b: &B, epsilon: f32) -> bool {
+    touching_axes(a, b, epsilon).is_some_and(|touching| touching > 1)
+}
+
+/// Boxes overlap or touch in any way
+pub fn intersects<B: Bounds>(a: &B, b: &B, epsilon: f32) -> bool {
+    touching_axes(a, b, epsilon).is_some()
+}
+
+/// `inner` lies within `outer`, allowing it to stick out by up to `epsilon`
+pub fn contains<B: Bounds>(outer: &B, inner: &B, epsilon: f32) -> bool {
+    (0..B::AXES).all(|axis| {
+        outer.min(axis) - epsilon <= inner.min(axis) && inner.max(axis) <= outer.max(axis) + epsilon
+    })
+}
+
+/// Repeatedly replace pairs of boxes with their merge until no pair can be merged
+pub(super) fn merge_all<T>(aabbs: &mut Vec<T>, merge: impl Fn(&T, &T) -> Option<T>) {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        let mut a = 0;
+        while a < aabbs.len() {
+            let mut b = a + 1;
+            while b < aabbs.len() {
+                if let Some(aabb) = merge(&aabbs[a], &aabbs[b]) {
+                    aabbs.swap_remove(b);
+                    aabbs[a] = aabb;
+                    merged = true;
+                    b = a + 1;
+                } else {
+                    b += 1;
+                }
+            }
+            a += 1;
+        }
+    }
+}
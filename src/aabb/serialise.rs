@@ -0,0 +1,197 @@
+use std::io::{Read, Write};
+
+use anyhow::{bail, Result};
+use bevy::math::{IVec3, UVec3};
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::aabb_3d::Aabb3D;
+use super::optimise_world::{SubChunk, SubChunkHalo, SubChunkNavMesh, SubChunkSource};
+
+/// Version of the binary and JSON formats, bumped whenever the layout of a stored type changes
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Largest amount of data decoded by `read_binary`, so a corrupt length can't make it read without end
+const MAX_BINARY_SIZE: u64 = 1 << 30;
+
+const SUB_CHUNK_MAGIC: [u8; 4] = *b"WSUB";
+const NAV_MESH_MAGIC: [u8; 4] = *b"WNAV";
+
+/// Input to `SubChunk::new_with_halo`, stored rather than the derived masks and merged boxes so
+/// loading rebuilds them with the current code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubChunkData {
+    pub location: IVec3,
+    /// Collision boxes of each non-empty block, relative to the sub chunk origin
+    pub blocks: Vec<(UVec3, Vec<Aabb3D>)>,
+    /// Inclusive bounds of the halo, relative to the sub chunk origin
+    pub halo_min: IVec3,
+    pub halo_max: IVec3,
+    /// Collision boxes of each non-empty halo block, relative to the sub chunk origin
+    pub halo: Vec<(IVec3, Vec<Aabb3D>)>,
+}
+
+impl SubChunkData {
+    pub fn new(sub_chunk: &SubChunk) -> Self {
+        let mut blocks = vec![];
+        for (z, plane) in sub_chunk.source().iter().enumerate() {
+            for (x, column) in plane.iter().enumerate() {
+                for (y, block) in column.iter().enumerate() {
+                    if !block.is_empty() {
+                        let pos = UVec3::new(x as u32, y as u32, z as u32);
+                        blocks.push((pos, block.to_vec()));
+                    }
+                }
+            }
+        }
+
+        let mut halo: Vec<(IVec3, Vec<Aabb3D>)> = sub_chunk
+            .halo()
+            .iter()
+            .map(|(pos, block)| (pos, block.to_vec()))
+            .collect();
+        halo.sort_by_key(|(pos, _)| (pos.z, pos.x, pos.y));
+
+        Self {
+            location: sub_chunk.location,
+            blocks,
+            halo_min: sub_chunk.halo().min(),
+            halo_max: sub_chunk.halo().max(),
+            halo,
+        }
+    }
+
+    pub fn source(&self) -> SubChunkSource {
+        let mut source: SubChunkSource = Default::default();
+        for (pos, block) in self.blocks.iter() {
+            if SubChunk::contains(pos.as_ivec3()) {
+                source[pos.z as usize][pos.x as usize][pos.y as usize] =
+                    block.iter().cloned().collect();
+            }
+        }
+        source
+    }
+
+    pub fn halo(&self) -> SubChunkHalo {
+        let mut halo = SubChunkHalo::with_bounds(self.halo_min, self.halo_max);
+        for (pos, block) in self.halo.iter() {
+            halo.insert(*pos, block.iter().cloned().collect());
+        }
+        halo
+    }
+
+    pub fn build(&self) -> SubChunk {
+        SubChunk::new_with_halo(self.location, self.source(), self.halo())
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    data: T,
+}
+
+pub(super) fn write_binary<T: Serialize>(
+    mut writer: impl Write,
+    magic: [u8; 4],
+    data: &T,
+) -> Result<()> {
+    writer.write_all(&magic)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    bincode::serialize_into(writer, data)?;
+    Ok(())
+}
+
+pub(super) fn read_binary<T: DeserializeOwned>(mut reader: impl Read, magic: [u8; 4]) -> Result<T> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header)?;
+    if header != magic {
+        bail!("Expected {:?} data, found {:?}", magic, header);
+    }
+    reader.read_exact(&mut header)?;
+    let version = u32::from_le_bytes(header);
+    if version != FORMAT_VERSION {
+        bail!(
+            "Unsupported format version {}, expected {}",
+            version,
+            FORMAT_VERSION
+        );
+    }
+    // Same encoding as `bincode::serialize_into` in `write_binary`, with a size limit
+    Ok(bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_BINARY_SIZE)
+        .deserialize_from(reader)?)
+}
+
+#[cfg(feature = "json")]
+pub(super) fn to_json<T: Serialize>(data: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Versioned {
+        version: FORMAT_VERSION,
+        data,
+    })?)
+}
+
+#[cfg(feature = "json")]
+pub(super) fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let versioned: Versioned<T> = serde_json::from_str(json)?;
+    if versioned.version != FORMAT_VERSION {
+        bail!(
+            "Unsupported format version {}, expected {}",
+            versioned.version,
+            FORMAT_VERSION
+        );
+    }
+    Ok(versioned.data)
+}
+
+impl SubChunk {
+    pub fn write_to(&self, writer: impl Write) -> Result<()> {
+        write_binary(writer, SUB_CHUNK_MAGIC, &SubChunkData::new(self))
+    }
+
+    pub fn read_from(reader: impl Read) -> Result<Self> {
+        Ok(read_binary::<SubChunkData>(reader, SUB_CHUNK_MAGIC)?.build())
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        to_json(&SubChunkData::new(self))
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(from_json::<SubChunkData>(json)?.build())
+    }
+}
+
+impl SubChunkNavMesh {
+    pub fn write_to(&self, writer: impl Write) -> Result<()> {
+        write_binary(writer, NAV_MESH_MAGIC, self)
+    }
+
+    pub fn read_from(reader: impl Read) -> Result<Self> {
+        Self::checked(read_binary(reader, NAV_MESH_MAGIC)?)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        to_json(self)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Self::checked(from_json(json)?)
+    }
+
+    /// Reject decoded nav meshes with indices that would panic when the mesh is used
+    fn checked(nav: Self) -> Result<Self> {
+        let errors = nav.validate();
+        if !errors.is_empty() {
+            bail!("Invalid nav mesh: {}", errors.join(", "));
+        }
+        Ok(nav)
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::{Read, Write};
+
+use anyhow::Result;
+use bevy::{math::IVec3, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use super::aabb_3d::Aabb3D;
+use super::agent::AgentParams;
+use super::optimise_world::{SubChunk, SubChunkHalo, SubChunkSource, SUB_CHUNK_SIZE};
+#[cfg(feature = "json")]
+use super::serialise::{from_json, to_json};
+use super::serialise::{read_binary, write_binary};
+
+const SNAPSHOT_MAGIC: [u8; 4] = *b"WSNP";
+
+/// Collision boxes of a region of the world, captured so nav mesh generation can be replayed
+/// without a server. Blocks inside the region without boxes are empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldSnapshot {
+    /// Inclusive lower bound of the captured region
+    pub min: IVec3,
+    /// Exclusive upper bound of the captured region
+    pub max: IVec3,
+    blocks: HashMap<IVec3, SmallVec<[Aabb3D; 1]>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WorldSnapshotData {
+    min: IVec3,
+    max: IVec3,
+    blocks: Vec<(IVec3, Vec<Aabb3D>)>,
+}
+
+impl WorldSnapshot {
+    pub fn new(min: IVec3, max: IVec3) -> Self {
+        Self {
+            min,
+            max,
+            blocks: Default::default(),
+        }
+    }
+
+    /// Empty snapshot of the sub chunks within `radius` blocks of `centre` and their halos sized
+    /// for `agent`, so each of those sub chunks can be built without missing blocks
+    pub fn around(centre: IVec3, radius: i32, agent: &AgentParams) -> Self {
+        let (halo_min, halo_max) = SubChunkHalo::bounds(agent);
+        let min = (centre - IVec3::splat(radius)).div_euclid(SUB_CHUNK_SIZE);
+        let max = (centre + IVec3::splat(radius) - IVec3::ONE).div_euclid(SUB_CHUNK_SIZE);
+        Self::new(
+            min * SUB_CHUNK_SIZE + halo_min,
+            max * SUB_CHUNK_SIZE + halo_max + IVec3::ONE,
+        )
+    }
+
+    pub fn contains(&self, pos: IVec3) -> bool {
+        pos.cmpge(self.min).all() && pos.cmplt(self.max).all()
+    }
+
+    /// Set the collision boxes of the block at the world position `pos`.
+    /// Positions outside of the region are ignored.
+    pub fn insert(&mut self, pos: IVec3, aabbs: SmallVec<[Aabb3D; 1]>) {
+        if !self.contains(pos) {
+            return;
+        }
+        if aabbs.is_empty() {
+            self.blocks.remove(&pos);
+        } else {
+            self.blocks.insert(pos, aabbs);
+        }
+    }
+
+    pub fn get(&self, pos: IVec3) -> &[Aabb3D] {
+        self.blocks.get(&pos).map_or(&[], |block| block.as_slice())
+    }
+
+    /// Number of blocks with collision boxes
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Indices of every sub chunk overlapping the region
+    pub fn sub_chunk_indices(&self) -> impl Iterator<Item = IVec3> {
+        let min = self.min.div_euclid(SUB_CHUNK_SIZE);
+        let max = (self.max - IVec3::ONE).div_euclid(SUB_CHUNK_SIZE);
+        (min.z..=max.z).flat_map(move |z| {
+            (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| IVec3 { x, y, z }))
+        })
+    }
+
+    /// Whether the sub chunk and its halo sized for `agent` are entirely inside the region. Blocks
+    /// outside of the region are read as empty, so other sub chunks get floors that aren't there.
+    pub fn covers(&self, sub_chunk_index: IVec3, agent: &AgentParams) -> bool {
+        let (halo_min, halo_max) = SubChunkHalo::bounds(agent);
+        let origin = sub_chunk_index * SUB_CHUNK_SIZE;
+        self.contains(origin + halo_min) && self.contains(origin + halo_max)
+    }
+
+    /// Collision boxes of a sub chunk in the form taken by `SubChunk::new`
+    pub fn source(&self, sub_chunk_index: IVec3) -> SubChunkSource {
+        let origin = sub_chunk_index * SUB_CHUNK_SIZE;
+        let mut source: SubChunkSource = Default::default();
+
+        for (z, plane) in source.iter_mut().enumerate() {
+            for (x, column) in plane.iter_mut().enumerate() {
+                for (y, block) in column.iter_mut().enumerate() {
+                    let pos = origin + IVec3::new(x as i32, y as i32, z as i32);
+                    block.extend(self.get(pos).iter().cloned());
+                }
+            }
+        }
+        source
+    }
+
+    /// Collision boxes around a sub chunk, in a halo sized for `agent`
+    pub fn halo(&self, sub_chunk_index: IVec3, agent: &AgentParams) -> SubChunkHalo {
+        let origin = sub_chunk_index * SUB_CHUNK_SIZE;
+        let mut halo = SubChunkHalo::new(agent);
+        let positions: Vec<IVec3> = halo.positions().collect();
+        for pos in positions {
+            halo.insert(pos, self.get(origin + pos).iter().cloned().collect());
+        }
+        halo
+    }
+
+    pub fn sub_chunk(&self, sub_chunk_index: IVec3, agent: &AgentParams) -> SubChunk {
+        SubChunk::new_with_halo(
+            sub_chunk_index,
+            self.source(sub_chunk_index),
+            self.halo(sub_chunk_index, agent),
+        )
+    }
+
+    fn to_data(&self) -> WorldSnapshotData {
+        let mut blocks: Vec<(IVec3, Vec<Aabb3D>)> = self
+            .blocks
+            .iter()
+            .map(|(pos, block)| (*pos, block.to_vec()))
+            .collect();
+        blocks.sort_by_key(|(pos, _)| (pos.z, pos.x, pos.y));
+
+        WorldSnapshotData {
+            min: self.min,
+            max: self.max,
+            blocks,
+        }
+    }
+
+    fn from_data(data: WorldSnapshotData) -> Self {
+        let mut snapshot = Self::new(data.min, data.max);
+        for (pos, block) in data.blocks {
+            snapshot.insert(pos, block.into_iter().collect());
+        }
+        snapshot
+    }
+
+    pub fn write_to(&self, writer: impl Write) -> Result<()> {
+        write_binary(writer, SNAPSHOT_MAGIC, &self.to_data())
+    }
+
+    pub fn read_from(reader: impl Read) -> Result<Self> {
+        Ok(Self::from_data(read_binary(reader, SNAPSHOT_MAGIC)?))
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        to_json(&self.to_data())
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(Self::from_data(from_json(json)?))
+    }
+}
@@ -3,6 +3,7 @@ use azalea_physics::collision::BlockWithShape;
 use azalea::{
     app::{Plugin, Update},
     chat::{ChatPacket, ChatReceivedEvent, SendChatEvent},
+    core::position::ChunkPos,
     ecs::{
         entity::Entity,
         event::{EventReader, EventWriter},
@@ -10,23 +11,37 @@ use azalea::{
         system::{Commands, Query, Res, ResMut},
     },
     entity::{metadata::Player, EntityUuid, LocalEntity, Position},
+    packet_handling::game::PacketEvent,
     pathfinder::{
         goals::BlockPosGoal,
         moves::{self},
         ComputePath, GotoEvent, StopPathfindingEvent,
     },
     prelude::*,
-    world::{InstanceContainer, InstanceName, MinecraftEntityId},
+    protocol::packets::game::ClientboundGamePacket,
+    world::{Instance, InstanceContainer, InstanceName, MinecraftEntityId},
     BlockPos,
 };
-use bevy::math::IVec3;
+use bevy::math::{IVec3, UVec3, Vec3};
 use bevy_rapier3d::plugin::{NoUserData, RapierPhysicsPlugin};
 use smallvec::SmallVec;
-use std::sync::{Arc, Mutex};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use wallace::aabb::{
     aabb_3d::Aabb3D,
-    optimise_world::{SubChunk, CHUNK_WIDTH, SUB_CHUNK_HEIGHT, SUB_CHUNK_SIZE},
+    agent::AgentParams,
+    nav_build::{NavBuildTasks, NavSubChunkBuilt},
+    nav_world::{NavSubChunk, NavWorld},
+    optimise_world::{SubChunkHalo, SUB_CHUNK_SIZE},
+    pathfind::find_path,
+    serialise::SubChunkData,
+    snapshot::WorldSnapshot,
 };
 
 const OWNER: [u8; 16] = [
@@ -118,9 +133,23 @@ impl Plugin for ChatControlPlugin {
                     follow_system,
                     chat_follow_system,
                     update_owner_system,
+                    nav_world_system,
+                    nav_world_evict_system,
+                    nav_world_rebuild_system,
+                    // Built sub chunks are inserted in the same tick they are polled, after
+                    // block updates have marked the ones which changed while being built
+                    (
+                        block_update_system,
+                        nav_build_poll_system,
+                        nav_world_insert_system,
+                    )
+                        .chain(),
                 ),
             )
-            .insert_resource(OwnerUuid(self.owner));
+            .add_event::<NavSubChunkBuilt>()
+            .insert_resource(OwnerUuid(self.owner))
+            .insert_resource(NavWorld::new(AgentParams::PLAYER))
+            .insert_resource(NavBuildTasks::new(AgentParams::PLAYER));
     }
 }
 
@@ -200,6 +229,7 @@ fn chat_follow_system(
     q_instance_name: Query<&InstanceName>,
     instance_container: Res<InstanceContainer>,
     debug_vis: ResMut<DebugVisChannels>,
+    mut nav_world: ResMut<NavWorld>,
 ) {
     for (client, content) in chat_events
         .read()
@@ -268,19 +298,84 @@ fn chat_follow_system(
 
                         debug_vis
                             .tx
-                            .blocking_send(InboundDebugVisEvent::AddCollisions { blocks })
+                            .blocking_send(InboundDebugVisEvent::AddCollisions {
+                                blocks,
+                                agent: AgentParams::PLAYER,
+                            })
                             .unwrap();
                     }
                 }
 
+                Some("dump") => {
+                    let radius = parse_dump_radius(cmd.next()).filter(|_| cmd.peek().is_none());
+                    if let Some(radius) = radius {
+                        let client_position: BlockPos = q_position
+                            .get(client)
+                            .expect("Couldn't get client position")
+                            .clone()
+                            .into();
+
+                        let world_name = q_instance_name
+                            .get(client)
+                            .expect("Couldn't get world name");
+                        let world_lock = instance_container
+                            .get(&world_name)
+                            .expect("Couldn't get instance");
+
+                        let world = world_lock.read();
+
+                        let centre = IVec3 {
+                            x: client_position.x,
+                            y: client_position.y,
+                            z: client_position.z,
+                        };
+                        let mut snapshot = WorldSnapshot::around(centre, radius, nav_world.agent());
+
+                        for z in snapshot.min.z..snapshot.max.z {
+                            for x in snapshot.min.x..snapshot.max.x {
+                                for y in snapshot.min.y..snapshot.max.y {
+                                    if let Some(block) =
+                                        world.get_block_state(&BlockPos { x, y, z })
+                                    {
+                                        snapshot.insert(
+                                            IVec3 { x, y, z },
+                                            SmallVec::from_iter(
+                                                block
+                                                    .shape()
+                                                    .to_aabbs()
+                                                    .into_iter()
+                                                    .map(|aabb| aabb.into()),
+                                            ),
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        drop(world);
+
+                        match write_snapshot(&snapshot) {
+                            Ok(path) => println!(
+                                "Saved {} blocks around {} to {}",
+                                snapshot.len(),
+                                centre,
+                                path.display()
+                            ),
+                            Err(err) => println!("Couldn't save snapshot: {}", err),
+                        }
+                    } else {
+                        println!(
+                            "Usage: dbg dump [radius], radius must be an integer from 1 to {}",
+                            DUMP_MAX_RADIUS
+                        );
+                    }
+                }
+
                 Some("nav") => {
                     let t_start = std::time::Instant::now();
 
-                    let client_position: BlockPos = q_position
+                    let client_position = q_position
                         .get(client)
-                        .expect("Couldn't get client position")
-                        .clone()
-                        .into();
+                        .expect("Couldn't get client position");
                     let world_name = q_instance_name
                         .get(client)
                         .expect("Couldn't get world name");
@@ -292,41 +387,23 @@ fn chat_follow_system(
 
                     let t_world_locked = std::time::Instant::now();
 
-                    let sub_chunk_index = IVec3 {
-                        x: client_position.x,
-                        y: client_position.y,
-                        z: client_position.z,
-                    }
-                    .div_euclid(SUB_CHUNK_SIZE);
-                    let sub_chunk_start = SUB_CHUNK_SIZE * sub_chunk_index;
-                    let sub_chunk_end = sub_chunk_start + SUB_CHUNK_SIZE;
-
-                    let mut sub_chunk_aabb_data: Box<
-                        [[[SmallVec<[Aabb3D; 1]>; SUB_CHUNK_HEIGHT]; CHUNK_WIDTH]; CHUNK_WIDTH],
-                    > = Default::default();
-
-                    for (k, z) in (sub_chunk_start.z..sub_chunk_end.z).enumerate() {
-                        for (i, x) in (sub_chunk_start.x..sub_chunk_end.x).enumerate() {
-                            for (j, y) in (sub_chunk_start.y..sub_chunk_end.y).enumerate() {
-                                if let Some(block) = world.get_block_state(&BlockPos { x, y, z }) {
-                                    sub_chunk_aabb_data[k][i][j] = SmallVec::from_iter(
-                                        block
-                                            .shape()
-                                            .to_aabbs()
-                                            .into_iter()
-                                            .map(|aabb| aabb.into()),
-                                    )
-                                }
-                            }
-                        }
-                    }
-
-                    let t_copied_data = std::time::Instant::now();
+                    let sub_chunk_index =
+                        NavWorld::sub_chunk_index(position_to_vec3(client_position));
+                    let Some(sub_chunk_data) =
+                        copy_sub_chunk(&world, sub_chunk_index, nav_world.agent())
+                    else {
+                        println!("Sub chunk {} isn't loaded", sub_chunk_index);
+                        continue;
+                    };
+                    drop(world);
+                    let sub_chunk = sub_chunk_data.build();
 
-                    let sub_chunk = SubChunk::new(sub_chunk_index, sub_chunk_aabb_data);
                     let t_sub_chunk = std::time::Instant::now();
 
-                    let nav = sub_chunk.build_nav_mesh();
+                    let NavSubChunk {
+                        sub_chunk,
+                        nav_mesh,
+                    } = nav_world.insert(sub_chunk);
                     let t_nav_mesh = std::time::Instant::now();
 
                     println!(
@@ -337,14 +414,9 @@ fn chat_follow_system(
                         "\tWorld lock: {:0.2}ms",
                         (t_world_locked - t_start).as_secs_f32() * 1000.0
                     );
-
                     println!(
-                        "\tWorld copy: {:0.2}ms",
-                        (t_copied_data - t_world_locked).as_secs_f32() * 1000.0
-                    );
-                    println!(
-                        "\tSub Chunk build: {:0.2}ms",
-                        (t_sub_chunk - t_copied_data).as_secs_f32() * 1000.0
+                        "\tWorld copy and Sub Chunk build: {:0.2}ms",
+                        (t_sub_chunk - t_world_locked).as_secs_f32() * 1000.0
                     );
                     println!(
                         "\tNav mesh build: {:0.2}ms",
@@ -354,12 +426,32 @@ fn chat_follow_system(
                     match cmd.next() {
                         Some("surf") if cmd.peek().is_none() => debug_vis
                             .tx
-                            .blocking_send(InboundDebugVisEvent::NavMesh { sub_chunk_nav: nav })
+                            .blocking_send(InboundDebugVisEvent::NavMesh {
+                                sub_chunk_nav: nav_mesh.clone(),
+                            })
                             .unwrap(),
                         Some("chunk") if cmd.peek().is_none() => debug_vis
                             .tx
-                            .blocking_send(InboundDebugVisEvent::SubChunk { sub_chunk })
+                            .blocking_send(InboundDebugVisEvent::SubChunk {
+                                sub_chunk: sub_chunk.clone(),
+                            })
                             .unwrap(),
+                        Some("path") if cmd.peek().is_none() => {
+                            if let Ok((_, owner_position)) = q_owner.get_single() {
+                                match find_path(
+                                    nav_world.as_ref(),
+                                    position_to_vec3(client_position),
+                                    position_to_vec3(owner_position),
+                                ) {
+                                    Some(path) => println!(
+                                        "Found path through {} nodes with cost {:0.2}",
+                                        path.nodes.len(),
+                                        path.cost
+                                    ),
+                                    None => println!("No path found"),
+                                }
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -400,3 +492,303 @@ fn follow_system(
         }
     }
 }
+
+fn position_to_vec3(pos: &Position) -> Vec3 {
+    Vec3 {
+        x: pos.x as f32,
+        y: pos.y as f32,
+        z: pos.z as f32,
+    }
+}
+
+/// Distance from the bot of the sub chunks saved by `dbg dump` when no radius is given
+const DUMP_DEFAULT_RADIUS: i32 = 16;
+
+/// Largest radius accepted by `dbg dump`, four sub chunks each way
+const DUMP_MAX_RADIUS: i32 = 64;
+
+/// Radius argument of `dbg dump`, `None` unless it's missing or an integer from 1 to
+/// `DUMP_MAX_RADIUS`
+fn parse_dump_radius(arg: Option<&str>) -> Option<i32> {
+    match arg {
+        None => Some(DUMP_DEFAULT_RADIUS),
+        Some(arg) => arg
+            .parse::<i32>()
+            .ok()
+            .filter(|radius| (1..=DUMP_MAX_RADIUS).contains(radius)),
+    }
+}
+
+/// Directory world snapshots from `dbg dump` are written to
+const SNAPSHOT_DIR: &str = "snapshots";
+
+/// Write a world snapshot to a new file in `SNAPSHOT_DIR`, returning its path
+fn write_snapshot(snapshot: &WorldSnapshot) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(SNAPSHOT_DIR)?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let path = Path::new(SNAPSHOT_DIR).join(format!("snapshot-{}.bin", timestamp));
+
+    let mut writer = BufWriter::new(File::create(&path)?);
+    snapshot.write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(path)
+}
+
+/// Copy the collision data of a sub chunk and its halo sized for `agent` out of the world, building
+/// the sub chunk is left until the world is unlocked. Returns `None` if the sub chunk isn't loaded.
+fn copy_sub_chunk(
+    world: &Instance,
+    sub_chunk_index: IVec3,
+    agent: &AgentParams,
+) -> Option<SubChunkData> {
+    let sub_chunk_start = SUB_CHUNK_SIZE * sub_chunk_index;
+    let sub_chunk_end = sub_chunk_start + SUB_CHUNK_SIZE;
+
+    let chunk_pos = ChunkPos::from(&BlockPos {
+        x: sub_chunk_start.x,
+        y: sub_chunk_start.y,
+        z: sub_chunk_start.z,
+    });
+    let min_y = world.chunks.min_y;
+    let max_y = min_y + world.chunks.height as i32;
+    if world.chunks.get(&chunk_pos).is_none()
+        || sub_chunk_start.y < min_y
+        || sub_chunk_end.y > max_y
+    {
+        return None;
+    }
+
+    let mut blocks = vec![];
+    for (k, z) in (sub_chunk_start.z..sub_chunk_end.z).enumerate() {
+        for (i, x) in (sub_chunk_start.x..sub_chunk_end.x).enumerate() {
+            for (j, y) in (sub_chunk_start.y..sub_chunk_end.y).enumerate() {
+                if let Some(block) = world.get_block_state(&BlockPos { x, y, z }) {
+                    let aabbs: Vec<Aabb3D> = block
+                        .shape()
+                        .to_aabbs()
+                        .into_iter()
+                        .map(|aabb| aabb.into())
+                        .collect();
+                    if !aabbs.is_empty() {
+                        blocks.push((UVec3::new(i as u32, j as u32, k as u32), aabbs));
+                    }
+                }
+            }
+        }
+    }
+
+    let halo_bounds = SubChunkHalo::new(agent);
+    let mut halo = vec![];
+    for pos in halo_bounds.positions() {
+        let block_pos = sub_chunk_start + pos;
+        if let Some(block) = world.get_block_state(&BlockPos {
+            x: block_pos.x,
+            y: block_pos.y,
+            z: block_pos.z,
+        }) {
+            let aabbs: Vec<Aabb3D> = block
+                .shape()
+                .to_aabbs()
+                .into_iter()
+                .map(|aabb| aabb.into())
+                .collect();
+            if !aabbs.is_empty() {
+                halo.push((pos, aabbs));
+            }
+        }
+    }
+
+    Some(SubChunkData {
+        location: sub_chunk_index,
+        blocks,
+        halo_min: halo_bounds.min(),
+        halo_max: halo_bounds.max(),
+        halo,
+    })
+}
+
+/// Sub chunks around each bot which are kept in the nav world
+const NAV_WORLD_RADIUS: IVec3 = IVec3 { x: 2, y: 1, z: 2 };
+/// Maximum number of sub chunks copied out of the world to be built per tick
+const NAV_WORLD_BUILD_BUDGET: usize = 16;
+/// Maximum number of sub chunks being built at once
+const NAV_WORLD_MAX_TASKS: usize = 64;
+
+fn nav_world_system(
+    nav_world: Res<NavWorld>,
+    mut nav_tasks: ResMut<NavBuildTasks>,
+    q_bot: Query<(&Position, &InstanceName), With<BotMarker>>,
+    instance_container: Res<InstanceContainer>,
+) {
+    let mut budget =
+        NAV_WORLD_BUILD_BUDGET.min(NAV_WORLD_MAX_TASKS.saturating_sub(nav_tasks.len()));
+
+    for (position, world_name) in q_bot.iter() {
+        let Some(world_lock) = instance_container.get(world_name) else {
+            continue;
+        };
+        let world = world_lock.read();
+        let centre = NavWorld::sub_chunk_index(position_to_vec3(position));
+
+        for z in -NAV_WORLD_RADIUS.z..=NAV_WORLD_RADIUS.z {
+            for x in -NAV_WORLD_RADIUS.x..=NAV_WORLD_RADIUS.x {
+                for y in -NAV_WORLD_RADIUS.y..=NAV_WORLD_RADIUS.y {
+                    if budget == 0 {
+                        return;
+                    }
+
+                    let index = centre + IVec3 { x, y, z };
+                    if nav_world.contains(index) || nav_tasks.contains(index) {
+                        continue;
+                    }
+                    if let Some(sub_chunk_data) = copy_sub_chunk(&world, index, nav_tasks.agent()) {
+                        nav_tasks.spawn(sub_chunk_data);
+                        budget -= 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sub chunks beyond `NAV_WORLD_RADIUS` kept in the nav world, so that they aren't rebuilt when a
+/// bot moves back and forth across a sub chunk border
+const NAV_WORLD_EVICT_MARGIN: IVec3 = IVec3::ONE;
+
+/// Remove sub chunks which are far from every bot from the nav world, and stop building them
+fn nav_world_evict_system(
+    mut nav_world: ResMut<NavWorld>,
+    mut nav_tasks: ResMut<NavBuildTasks>,
+    q_bot: Query<&Position, With<BotMarker>>,
+) {
+    let centres: Vec<IVec3> = q_bot
+        .iter()
+        .map(|position| NavWorld::sub_chunk_index(position_to_vec3(position)))
+        .collect();
+
+    let radius = NAV_WORLD_RADIUS + NAV_WORLD_EVICT_MARGIN;
+    nav_world.evict(&centres, radius);
+    // Builds started before the bots moved away would otherwise be inserted once they finish
+    nav_tasks.evict(&centres, radius);
+}
+
+/// Send the indices of the sub chunks which have finished building as events
+fn nav_build_poll_system(
+    mut nav_tasks: ResMut<NavBuildTasks>,
+    mut ev_built: EventWriter<NavSubChunkBuilt>,
+) {
+    for index in nav_tasks.poll() {
+        ev_built.send(NavSubChunkBuilt(index));
+    }
+}
+
+/// Move built sub chunks into the nav world
+fn nav_world_insert_system(
+    mut nav_world: ResMut<NavWorld>,
+    mut nav_tasks: ResMut<NavBuildTasks>,
+    mut ev_built: EventReader<NavSubChunkBuilt>,
+) {
+    for NavSubChunkBuilt(index) in ev_built.read() {
+        nav_tasks.insert_built(&mut nav_world, *index);
+    }
+}
+
+/// Maximum number of dirty sub chunks copied out of the world to be rebuilt per tick
+const NAV_WORLD_REBUILD_BUDGET: usize = 16;
+
+fn block_update_system(
+    mut packet_events: EventReader<PacketEvent>,
+    mut nav_world: ResMut<NavWorld>,
+    mut nav_tasks: ResMut<NavBuildTasks>,
+) {
+    let mut mark_block_dirty = |pos: IVec3| {
+        nav_world.mark_block_dirty(pos);
+        // Sub chunks being built may have been copied before the change, they are marked dirty
+        // in the nav world when they are inserted
+        for index in nav_world.block_sub_chunks(pos) {
+            nav_tasks.mark_dirty(index);
+        }
+    };
+
+    for event in packet_events.read() {
+        match &event.packet {
+            ClientboundGamePacket::BlockUpdate(packet) => {
+                mark_block_dirty(IVec3 {
+                    x: packet.pos.x,
+                    y: packet.pos.y,
+                    z: packet.pos.z,
+                });
+            }
+            ClientboundGamePacket::SectionBlocksUpdate(packet) => {
+                let section_start = SUB_CHUNK_SIZE
+                    * IVec3 {
+                        x: packet.section_pos.x,
+                        y: packet.section_pos.y,
+                        z: packet.section_pos.z,
+                    };
+                for state in packet.states.iter() {
+                    mark_block_dirty(
+                        section_start
+                            + IVec3 {
+                                x: state.pos.x as i32,
+                                y: state.pos.y as i32,
+                                z: state.pos.z as i32,
+                            },
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Copy the dirty sub chunks nearest to a bot out of the world to be rebuilt.
+///
+/// The nav world doesn't track which instance a sub chunk is in, so each one is copied from the
+/// instance of the bot nearest to it.
+fn nav_world_rebuild_system(
+    mut nav_world: ResMut<NavWorld>,
+    mut nav_tasks: ResMut<NavBuildTasks>,
+    q_bot: Query<(&Position, &InstanceName), With<BotMarker>>,
+    instance_container: Res<InstanceContainer>,
+) {
+    let budget = NAV_WORLD_REBUILD_BUDGET.min(NAV_WORLD_MAX_TASKS.saturating_sub(nav_tasks.len()));
+    if nav_world.dirty_len() == 0 || budget == 0 {
+        return;
+    }
+    let bots: Vec<(IVec3, &InstanceName)> = q_bot
+        .iter()
+        .map(|(position, world_name)| {
+            (
+                NavWorld::sub_chunk_index(position_to_vec3(position)),
+                world_name,
+            )
+        })
+        .collect();
+    if bots.is_empty() {
+        return;
+    }
+    let centres: Vec<IVec3> = bots.iter().map(|(centre, _)| *centre).collect();
+
+    for index in nav_world.take_dirty_nearest(&centres, budget) {
+        let (_, world_name) = bots
+            .iter()
+            .min_by_key(|(centre, _)| (index - *centre).abs().max_element())
+            .expect("There is at least one bot");
+        let Some(world_lock) = instance_container.get(world_name) else {
+            // Left dirty until the instance is available again
+            nav_world.mark_dirty(index);
+            continue;
+        };
+        let world = world_lock.read();
+        match copy_sub_chunk(&world, index, nav_tasks.agent()) {
+            Some(sub_chunk_data) => {
+                nav_tasks.spawn(sub_chunk_data);
+            }
+            None => {
+                nav_tasks.cancel(index);
+                nav_world.remove(index);
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use bevy::{math::vec3, pbr::ExtendedMaterial, prelude::*, render::mesh::Indices,
 use tokio::sync::mpsc::{Receiver, Sender};
 use wallace::{
     aabb::{
+        agent::AgentParams,
         debug_aabb_material::DebugAabbMaterial,
         debug_surface_material::DebugSurfaceMaterial,
         optimise_world::{SubChunk, SubChunkNavMesh, SUB_CHUNK_SIZE},
@@ -83,7 +84,7 @@ fn debug_vis_system(
                 }
                 player_paths.clear();
             }
-            InboundDebugVisEvent::AddCollisions { blocks } => {
+            InboundDebugVisEvent::AddCollisions { blocks, agent } => {
                 let mut collider_mesh_builder = MeshBuilder::new();
                 let mut nav_mesh_builder = MeshBuilder::new();
 
@@ -108,12 +109,12 @@ fn debug_vis_system(
 
                         nav_mesh_builder.add_mesh(
                             &shape::Box {
-                                min_x: aabb.min_x as f32 - 0.3f32,
-                                min_y: aabb.min_y as f32 - 1.8f32,
-                                min_z: aabb.min_z as f32 - 0.3f32,
-                                max_x: aabb.max_x as f32 + 0.3f32,
+                                min_x: aabb.min_x as f32 - agent.radius,
+                                min_y: aabb.min_y as f32 - agent.height,
+                                min_z: aabb.min_z as f32 - agent.radius,
+                                max_x: aabb.max_x as f32 + agent.radius,
                                 max_y: aabb.max_y as f32 + 0.0f32,
-                                max_z: aabb.max_z as f32 + 0.3f32,
+                                max_z: aabb.max_z as f32 + agent.radius,
                             }
                             .into(),
                             Transform::from_translation(Vec3 {
@@ -300,6 +301,7 @@ pub enum InboundDebugVisEvent {
     Clear,
     AddCollisions {
         blocks: Vec<DebugBlock>,
+        agent: AgentParams,
     },
     PlayerPosition {
         uuid: [u8; 16],
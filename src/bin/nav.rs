@@ -0,0 +1,108 @@
+//! Build nav meshes for a world snapshot without a server or window.
+//!
+//! Usage: `nav <snapshot> [--sneaking]`
+//!
+//! Snapshots are written by the `dbg dump <radius>` chat command of the ecs bot. Sub chunks whose
+//! blocks or halo are partly outside the snapshot are skipped. Exits with an error if any nav mesh
+//! fails validation.
+
+use std::{fs::File, io::BufReader, process::ExitCode, time::Instant};
+
+use wallace::aabb::{agent::AgentParams, snapshot::WorldSnapshot};
+
+fn main() -> anyhow::Result<ExitCode> {
+    let mut path = None;
+    let mut agent = AgentParams::PLAYER;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--sneaking" => agent = agent.sneaking(),
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument {}", arg);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("Usage: nav <snapshot> [--sneaking]");
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let t_start = Instant::now();
+    let snapshot = WorldSnapshot::read_from(BufReader::new(File::open(&path)?))?;
+    println!(
+        "Loaded {} blocks from {} to {} in {:0.2}ms",
+        snapshot.len(),
+        snapshot.min,
+        snapshot.max,
+        t_start.elapsed().as_secs_f32() * 1000.0
+    );
+
+    let mut total_sub_chunk = 0.0;
+    let mut total_nav_mesh = 0.0;
+    let mut total_nodes = 0;
+    let mut error_count = 0;
+    let mut skipped = 0;
+
+    for index in snapshot.sub_chunk_indices() {
+        if !snapshot.covers(index, &agent) {
+            skipped += 1;
+            continue;
+        }
+
+        let t_start = Instant::now();
+        let sub_chunk = snapshot.sub_chunk(index, &agent);
+        let t_sub_chunk = Instant::now();
+        let nav_mesh = sub_chunk.build_nav_mesh(&agent);
+        let t_nav_mesh = Instant::now();
+
+        let sub_chunk_ms = (t_sub_chunk - t_start).as_secs_f32() * 1000.0;
+        let nav_mesh_ms = (t_nav_mesh - t_sub_chunk).as_secs_f32() * 1000.0;
+        total_sub_chunk += sub_chunk_ms;
+        total_nav_mesh += nav_mesh_ms;
+
+        let nodes: usize = nav_mesh.floor.iter().map(|layer| layer.nodes.len()).sum();
+        let links: usize = nav_mesh
+            .floor
+            .iter()
+            .flat_map(|layer| layer.nodes.iter())
+            .map(|node| node.links.len())
+            .sum();
+        total_nodes += nodes;
+
+        println!(
+            "Sub chunk {}: {} floor layers, {} ceiling layers, {} nodes, {} links",
+            index,
+            nav_mesh.floor.len(),
+            nav_mesh.ceiling.len(),
+            nodes,
+            links
+        );
+        println!(
+            "\tSub chunk build: {:0.2}ms, nav mesh build: {:0.2}ms",
+            sub_chunk_ms, nav_mesh_ms
+        );
+
+        for error in nav_mesh.validate() {
+            println!("\tError: {}", error);
+            error_count += 1;
+        }
+    }
+
+    if skipped > 0 {
+        println!(
+            "Skipped {} sub chunks not covered by the snapshot along with their halo",
+            skipped
+        );
+    }
+    println!(
+        "Total: {} nodes, sub chunk build {:0.2}ms, nav mesh build {:0.2}ms, {} errors",
+        total_nodes, total_sub_chunk, total_nav_mesh, error_count
+    );
+
+    Ok(if error_count == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
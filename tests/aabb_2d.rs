@@ -239,3 +239,84 @@ mod aabb_2d_subtract {
         assert_eq!(c, vec![a]);
     }
 }
+
+#[cfg(test)]
+mod aabb_2d_union_all {
+    use wallace::aabb::aabb_2d::*;
+
+    fn square(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Aabb2D {
+        Aabb2D {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    fn area(aabbs: &[Aabb2D]) -> f32 {
+        aabbs.iter().map(|aabb| aabb.area()).sum()
+    }
+
+    fn assert_disjoint(aabbs: &[Aabb2D]) {
+        for a in 0..aabbs.len() {
+            for b in a + 1..aabbs.len() {
+                assert!(
+                    !aabbs[a].overlaps(&aabbs[b]),
+                    "{:?} {:?}",
+                    aabbs[a],
+                    aabbs[b]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert!(Aabb2D::union_all(vec![]).is_empty());
+    }
+
+    #[test]
+    fn row() {
+        let result = Aabb2D::union_all((0..4).map(|x| square(x as f32, 0.0, x as f32 + 1.0, 1.0)));
+        assert_eq!(vec![square(0.0, 0.0, 4.0, 1.0)], result);
+    }
+
+    #[test]
+    fn grid() {
+        let mut aabbs = vec![];
+        for x in 0..4 {
+            for y in 0..4 {
+                let (x, y) = (x as f32, y as f32);
+                aabbs.push(square(x, y, x + 1.0, y + 1.0));
+            }
+        }
+        assert_eq!(vec![square(0.0, 0.0, 4.0, 4.0)], Aabb2D::union_all(aabbs));
+    }
+
+    #[test]
+    fn overlapping() {
+        let result = Aabb2D::union_all(vec![
+            square(0.0, 0.0, 2.0, 2.0),
+            square(1.0, 1.0, 3.0, 3.0),
+            square(-1.0, 0.5, 0.5, 1.0),
+        ]);
+        assert_disjoint(&result);
+        assert!((area(&result) - (4.0 + 3.0 + 0.5)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn contained() {
+        let result = Aabb2D::union_all(vec![
+            square(0.25, 0.25, 0.75, 0.75),
+            square(0.0, 0.0, 1.0, 1.0),
+        ]);
+        assert_eq!(vec![square(0.0, 0.0, 1.0, 1.0)], result);
+    }
+
+    #[test]
+    fn degenerate() {
+        let result =
+            Aabb2D::union_all(vec![square(0.0, 0.0, 0.0, 1.0), square(0.0, 0.0, 1.0, 1.0)]);
+        assert_eq!(vec![square(0.0, 0.0, 1.0, 1.0)], result);
+    }
+}
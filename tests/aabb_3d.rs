@@ -65,4 +65,246 @@ mod aabb_3d_union {
         assert_eq!(vec![Aabb3D([0.0, 0.0, 0.0, 1.5, 1.0, 1.0])], a.union(&b));
         assert_eq!(vec![Aabb3D([0.0, 0.0, 0.0, 1.5, 1.0, 1.0])], b.union(&a));
     }
+
+    #[test]
+    fn overlap_3_axes() {
+        let a = Aabb3D([0.0, 0.0, 0.0, 2.0, 2.0, 2.0]);
+        let b = Aabb3D([1.0, 1.0, 1.0, 3.0, 3.0, 3.0]);
+        for union in [a.union(&b), b.union(&a)] {
+            assert_eq!(4, union.len());
+            assert_eq!(15.0, union.iter().map(Aabb3D::volume).sum::<f32>());
+        }
+    }
+}
+
+#[cfg(test)]
+mod aabb_3d_union_all {
+    use wallace::aabb::aabb_3d::*;
+
+    fn volume(aabbs: &[Aabb3D]) -> f32 {
+        aabbs.iter().map(|aabb| aabb.volume()).sum()
+    }
+
+    fn assert_disjoint(aabbs: &[Aabb3D]) {
+        for a in 0..aabbs.len() {
+            for b in a + 1..aabbs.len() {
+                assert!(
+                    !aabbs[a].overlaps(&aabbs[b]),
+                    "{:?} {:?}",
+                    aabbs[a],
+                    aabbs[b]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn overlap_1_axis() {
+        let a = Aabb3D([0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let b = Aabb3D([0.5, 0.0, 0.0, 1.5, 1.0, 1.0]);
+        assert_eq!(
+            vec![Aabb3D([0.0, 0.0, 0.0, 1.5, 1.0, 1.0])],
+            Aabb3D::union_all(vec![a, b])
+        );
+    }
+
+    #[test]
+    fn stairs() {
+        // Bottom slab and a quarter block on top, as returned for a stair shape
+        let result = Aabb3D::union_all(vec![
+            Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0]),
+            Aabb3D([0.0, 0.5, 0.5, 1.0, 1.0, 1.0]),
+        ]);
+        assert_eq!(2, result.len());
+        assert!((volume(&result) - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn overlapping() {
+        let result = Aabb3D::union_all(vec![
+            Aabb3D([0.0, 0.0, 0.0, 2.0, 2.0, 2.0]),
+            Aabb3D([1.0, 1.0, 1.0, 3.0, 3.0, 3.0]),
+            Aabb3D([0.5, 0.5, -1.0, 1.5, 1.5, 3.0]),
+        ]);
+        assert_disjoint(&result);
+        // 8 + 8 - 1 + 4 - (2 inside first + 0.5 inside second, 0.25 inside both)
+        assert!((volume(&result) - (15.0 + 4.0 - 2.0 - 0.5 + 0.25)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn grid() {
+        let mut aabbs = vec![];
+        for x in 0..3 {
+            for y in 0..3 {
+                for z in 0..3 {
+                    let (x, y, z) = (x as f32, y as f32, z as f32);
+                    aabbs.push(Aabb3D([x, y, z, x + 1.0, y + 1.0, z + 1.0]));
+                }
+            }
+        }
+        assert_eq!(
+            vec![Aabb3D([0.0, 0.0, 0.0, 3.0, 3.0, 3.0])],
+            Aabb3D::union_all(aabbs)
+        );
+    }
+}
+
+#[cfg(test)]
+mod aabb_3d_subtract {
+    use bevy::math::Vec3;
+    use wallace::aabb::aabb_3d::*;
+
+    fn volume(aabbs: &[Aabb3D]) -> f32 {
+        aabbs.iter().map(|aabb| aabb.volume()).sum()
+    }
+
+    fn assert_disjoint(aabbs: &[Aabb3D]) {
+        for a in 0..aabbs.len() {
+            for b in a + 1..aabbs.len() {
+                assert!(
+                    !aabbs[a].overlaps(&aabbs[b]),
+                    "{:?} {:?}",
+                    aabbs[a],
+                    aabbs[b]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn centre() {
+        let a = Aabb3D([0.0, 0.0, 0.0, 3.0, 3.0, 3.0]);
+        let b = Aabb3D([1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+        let pieces = a.subtract(&b);
+
+        assert_eq!(6, pieces.len());
+        assert_disjoint(&pieces);
+        assert!((volume(&pieces) - 26.0).abs() < 1e-4);
+        assert!(pieces.iter().all(|piece| !piece.overlaps(&b)));
+    }
+
+    #[test]
+    fn disjoint() {
+        let a = Aabb3D::FULL_BLOCK;
+        let b = Aabb3D([2.0, 0.0, 0.0, 3.0, 1.0, 1.0]);
+        assert_eq!(vec![a.clone()], a.subtract(&b));
+
+        let c = Aabb3D([0.0, 1.0, 0.0, 1.0, 2.0, 1.0]);
+        assert_eq!(vec![a.clone()], a.subtract(&c));
+    }
+
+    #[test]
+    fn covered() {
+        let a = Aabb3D::FULL_BLOCK;
+        let b = Aabb3D([-1.0, -1.0, -1.0, 2.0, 2.0, 2.0]);
+        assert!(a.subtract(&b).is_empty());
+    }
+
+    #[test]
+    fn headroom() {
+        // Slab above the lower half of a two block tall volume
+        let a = Aabb3D([0.0, 1.0, 0.0, 1.0, 2.8, 1.0]);
+        let b = Aabb3D([0.0, 2.5, 0.0, 1.0, 3.0, 1.0]);
+        assert_eq!(vec![Aabb3D([0.0, 1.0, 0.0, 1.0, 2.5, 1.0])], a.subtract(&b));
+    }
+
+    #[test]
+    fn intersection() {
+        let a = Aabb3D([0.0, 0.0, 0.0, 2.0, 2.0, 2.0]);
+        let b = Aabb3D([1.0, 1.0, 1.0, 3.0, 3.0, 3.0]);
+        assert_eq!(
+            Some(Aabb3D([1.0, 1.0, 1.0, 2.0, 2.0, 2.0])),
+            a.intersection(&b)
+        );
+
+        let c = Aabb3D([2.0, 0.0, 0.0, 3.0, 1.0, 1.0]);
+        assert_eq!(None, a.intersection(&c));
+    }
+
+    #[test]
+    fn clamp_inflate_translate() {
+        let a = Aabb3D([-1.0, 0.5, 0.0, 2.0, 1.0, 0.5]);
+        assert_eq!(
+            Aabb3D([0.0, 0.5, 0.0, 1.0, 1.0, 0.5]),
+            a.clamp(&Aabb3D::FULL_BLOCK)
+        );
+
+        assert_eq!(
+            Aabb3D([-0.5, 0.0, -0.5, 1.5, 1.0, 1.5]),
+            Aabb3D::FULL_BLOCK.inflate(Vec3::new(0.5, 0.0, 0.5))
+        );
+
+        assert_eq!(
+            Aabb3D([1.0, 2.0, 3.0, 2.0, 3.0, 4.0]),
+            Aabb3D::FULL_BLOCK.translate(Vec3::new(1.0, 2.0, 3.0))
+        );
+    }
+}
+
+#[cfg(test)]
+mod aabb_3d_cmp_faces {
+    use wallace::aabb::aabb_3d::*;
+
+    #[test]
+    fn identical_face() {
+        let a = Aabb3D::FULL_BLOCK;
+        let b = Aabb3D([1.0, 0.0, 0.0, 2.0, 1.0, 1.0]);
+
+        let faces = a.cmp_faces(&b);
+        assert_eq!(ContactType::Superset, faces[Axis::X as usize]);
+        for direction in [Axis::Y, Axis::Z, Axis::NegX, Axis::NegY, Axis::NegZ] {
+            assert_eq!(ContactType::None, faces[direction as usize]);
+        }
+        assert_eq!(ContactType::Superset, b.cmp_face(&a, Axis::NegX));
+    }
+
+    #[test]
+    fn slab_below() {
+        let a = Aabb3D([0.0, 1.0, 0.0, 1.0, 2.0, 1.0]);
+        let slab = Aabb3D([0.0, 0.5, 0.0, 1.0, 1.0, 0.5]);
+
+        assert_eq!(ContactType::Superset, a.cmp_face(&slab, Axis::NegY));
+        assert_eq!(ContactType::Subset, slab.cmp_face(&a, Axis::Y));
+    }
+
+    #[test]
+    fn mixed() {
+        let a = Aabb3D::FULL_BLOCK;
+        let b = Aabb3D([0.5, 0.0, 1.0, 1.5, 1.0, 2.0]);
+
+        assert_eq!(ContactType::Mixed, a.cmp_face(&b, Axis::Z));
+        assert_eq!(ContactType::Mixed, b.cmp_face(&a, Axis::NegZ));
+    }
+
+    #[test]
+    fn edge_and_corner() {
+        let a = Aabb3D::FULL_BLOCK;
+        let edge = Aabb3D([1.0, 1.0, 0.0, 2.0, 2.0, 1.0]);
+        let corner = Aabb3D([1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+
+        assert!(a
+            .cmp_faces(&edge)
+            .iter()
+            .all(|contact| *contact == ContactType::None));
+        assert!(a
+            .cmp_faces(&corner)
+            .iter()
+            .all(|contact| *contact == ContactType::None));
+    }
+
+    #[test]
+    fn gap() {
+        let a = Aabb3D::FULL_BLOCK;
+        let b = Aabb3D([1.5, 0.0, 0.0, 2.5, 1.0, 1.0]);
+        assert_eq!(ContactType::None, a.cmp_face(&b, Axis::X));
+    }
+
+    #[test]
+    fn axis() {
+        for direction in Axis::ALL {
+            assert_eq!(direction.axis(), direction.opposite().axis());
+            assert_ne!(direction.is_positive(), direction.opposite().is_positive());
+            assert_eq!(direction, Axis::ALL[direction as usize]);
+        }
+    }
 }
@@ -0,0 +1,272 @@
+//! Invariants of the box set operations, checked on random boxes.
+//!
+//! Box coordinates are multiples of `STEP` in `0..=EXTENT`, so coincident edges and faces come up
+//! often. Coverage is checked by sampling points between the grid lines, which never lie on an
+//! edge, so the sampled areas and volumes are exact.
+
+#[cfg(test)]
+mod aabb_2d_properties {
+    use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
+    use wallace::aabb::aabb_2d::{Aabb2D, Point2D};
+
+    const STEP: f32 = 0.25;
+    const EXTENT: u8 = 16;
+
+    #[derive(Debug, Clone)]
+    struct Rect(Aabb2D);
+
+    fn interval(g: &mut Gen) -> (f32, f32) {
+        let a = u8::arbitrary(g) % (EXTENT + 1);
+        let mut b = u8::arbitrary(g) % EXTENT;
+        if b >= a {
+            b += 1;
+        }
+        (a.min(b) as f32 * STEP, a.max(b) as f32 * STEP)
+    }
+
+    impl Arbitrary for Rect {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let (min_x, max_x) = interval(g);
+            let (min_y, max_y) = interval(g);
+            Rect(Aabb2D {
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+            })
+        }
+    }
+
+    /// Centres of the grid cells
+    fn samples() -> impl Iterator<Item = Point2D> {
+        (0..EXTENT).flat_map(|x| {
+            (0..EXTENT).map(move |y| Point2D {
+                x: (x as f32 + 0.5) * STEP,
+                y: (y as f32 + 0.5) * STEP,
+            })
+        })
+    }
+
+    fn covered(aabbs: &[Aabb2D], point: &Point2D) -> usize {
+        aabbs.iter().filter(|aabb| aabb.contains(point)).count()
+    }
+
+    /// Every sample is covered exactly as often as `expected` says
+    fn tiles(aabbs: &[Aabb2D], expected: impl Fn(&Point2D) -> bool) -> bool {
+        samples().all(|point| covered(aabbs, &point) == expected(&point) as usize)
+    }
+
+    fn is_inside(inner: &Aabb2D, outer: &Aabb2D) -> bool {
+        outer.min_x <= inner.min_x
+            && outer.min_y <= inner.min_y
+            && inner.max_x <= outer.max_x
+            && inner.max_y <= outer.max_y
+    }
+
+    #[test]
+    fn union_covers_both() {
+        fn prop(a: Rect, b: Rect) -> bool {
+            let union = a.0.union(&b.0);
+            tiles(&union, |point| a.0.contains(point) || b.0.contains(point))
+        }
+        quickcheck(prop as fn(Rect, Rect) -> bool);
+    }
+
+    #[test]
+    fn union_area() {
+        fn prop(a: Rect, b: Rect) -> bool {
+            let union = a.0.union(&b.0);
+            let cell_area = STEP * STEP;
+            let sampled = samples()
+                .filter(|point| a.0.contains(point) || b.0.contains(point))
+                .count() as f32
+                * cell_area;
+            let area: f32 = union.iter().map(Aabb2D::area).sum();
+            (area - sampled).abs() < cell_area / 2.0
+        }
+        quickcheck(prop as fn(Rect, Rect) -> bool);
+    }
+
+    #[test]
+    fn subtract_disjoint_from_subtrahend() {
+        fn prop(a: Rect, b: Rect) -> bool {
+            a.0.subtract(&b.0)
+                .iter()
+                .all(|piece| !piece.overlaps(&b.0) && is_inside(piece, &a.0))
+        }
+        quickcheck(prop as fn(Rect, Rect) -> bool);
+    }
+
+    #[test]
+    fn subtract_covers_difference() {
+        fn prop(a: Rect, b: Rect) -> bool {
+            let pieces = a.0.subtract(&b.0);
+            tiles(&pieces, |point| a.0.contains(point) && !b.0.contains(point))
+        }
+        quickcheck(prop as fn(Rect, Rect) -> bool);
+    }
+
+    #[test]
+    fn cut_tiles_original() {
+        fn prop(a: Rect, b: Rect) -> TestResult {
+            let Some((before, after)) = a.0.cut(&b.0) else {
+                return TestResult::discard();
+            };
+            let pieces = [before, after];
+            TestResult::from_bool(
+                tiles(&pieces, |point| b.0.contains(point))
+                    && pieces.iter().all(|piece| is_inside(piece, &b.0))
+                    && !pieces[1].overlaps(&a.0),
+            )
+        }
+        quickcheck(prop as fn(Rect, Rect) -> TestResult);
+    }
+
+    #[test]
+    fn clamp_inside_bounds() {
+        fn prop(a: Rect, b: Rect) -> bool {
+            let clamped = a.0.clamp(&b.0);
+            let intersection = Aabb2D {
+                min_x: a.0.min_x.max(b.0.min_x),
+                min_y: a.0.min_y.max(b.0.min_y),
+                max_x: a.0.max_x.min(b.0.max_x),
+                max_y: a.0.max_y.min(b.0.max_y),
+            };
+            is_inside(&clamped, &b.0)
+                && (!a.0.overlaps(&b.0) || clamped == intersection)
+                && clamped.clamp(&b.0) == clamped
+        }
+        quickcheck(prop as fn(Rect, Rect) -> bool);
+    }
+}
+
+#[cfg(test)]
+mod aabb_3d_properties {
+    use bevy::math::Vec3;
+    use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
+    use wallace::aabb::aabb_3d::Aabb3D;
+
+    const STEP: f32 = 0.25;
+    const EXTENT: u8 = 12;
+
+    #[derive(Debug, Clone)]
+    struct Cuboid(Aabb3D);
+
+    fn interval(g: &mut Gen) -> (f32, f32) {
+        let a = u8::arbitrary(g) % (EXTENT + 1);
+        let mut b = u8::arbitrary(g) % EXTENT;
+        if b >= a {
+            b += 1;
+        }
+        (a.min(b) as f32 * STEP, a.max(b) as f32 * STEP)
+    }
+
+    impl Arbitrary for Cuboid {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let (min_x, max_x) = interval(g);
+            let (min_y, max_y) = interval(g);
+            let (min_z, max_z) = interval(g);
+            Cuboid(Aabb3D([min_x, min_y, min_z, max_x, max_y, max_z]))
+        }
+    }
+
+    /// Centres of the grid cells
+    fn samples() -> impl Iterator<Item = Vec3> {
+        (0..EXTENT).flat_map(|x| {
+            (0..EXTENT).flat_map(move |y| {
+                (0..EXTENT).map(move |z| (Vec3::new(x as f32, y as f32, z as f32) + 0.5) * STEP)
+            })
+        })
+    }
+
+    fn contains(aabb: &Aabb3D, point: Vec3) -> bool {
+        (0..3).all(|axis| aabb.0[axis] < point[axis] && point[axis] < aabb.0[axis + 3])
+    }
+
+    /// Every sample is covered exactly as often as `expected` says
+    fn tiles(aabbs: &[Aabb3D], expected: impl Fn(Vec3) -> bool) -> bool {
+        samples().all(|point| {
+            aabbs.iter().filter(|aabb| contains(aabb, point)).count() == expected(point) as usize
+        })
+    }
+
+    fn is_inside(inner: &Aabb3D, outer: &Aabb3D) -> bool {
+        (0..3).all(|axis| outer.0[axis] <= inner.0[axis] && inner.0[axis + 3] <= outer.0[axis + 3])
+    }
+
+    #[test]
+    fn union_covers_both() {
+        fn prop(a: Cuboid, b: Cuboid) -> bool {
+            let union = a.0.union(&b.0);
+            tiles(&union, |point| {
+                contains(&a.0, point) || contains(&b.0, point)
+            })
+        }
+        quickcheck(prop as fn(Cuboid, Cuboid) -> bool);
+    }
+
+    #[test]
+    fn union_volume() {
+        fn prop(a: Cuboid, b: Cuboid) -> bool {
+            let union = a.0.union(&b.0);
+            let cell_volume = STEP * STEP * STEP;
+            let sampled = samples()
+                .filter(|point| contains(&a.0, *point) || contains(&b.0, *point))
+                .count() as f32
+                * cell_volume;
+            let volume: f32 = union.iter().map(Aabb3D::volume).sum();
+            (volume - sampled).abs() < cell_volume / 2.0
+        }
+        quickcheck(prop as fn(Cuboid, Cuboid) -> bool);
+    }
+
+    #[test]
+    fn subtract_disjoint_from_subtrahend() {
+        fn prop(a: Cuboid, b: Cuboid) -> bool {
+            let pieces = a.0.subtract(&b.0);
+            pieces.len() <= 6
+                && pieces
+                    .iter()
+                    .all(|piece| !piece.overlaps(&b.0) && is_inside(piece, &a.0))
+        }
+        quickcheck(prop as fn(Cuboid, Cuboid) -> bool);
+    }
+
+    #[test]
+    fn subtract_covers_difference() {
+        fn prop(a: Cuboid, b: Cuboid) -> bool {
+            let pieces = a.0.subtract(&b.0);
+            tiles(&pieces, |point| {
+                contains(&a.0, point) && !contains(&b.0, point)
+            })
+        }
+        quickcheck(prop as fn(Cuboid, Cuboid) -> bool);
+    }
+
+    #[test]
+    fn cut_tiles_original() {
+        fn prop(a: Cuboid, b: Cuboid) -> TestResult {
+            let Some((before, after)) = a.0.cut(&b.0) else {
+                return TestResult::discard();
+            };
+            let pieces = [before, after];
+            TestResult::from_bool(
+                tiles(&pieces, |point| contains(&b.0, point))
+                    && pieces.iter().all(|piece| is_inside(piece, &b.0))
+                    && !pieces[1].overlaps(&a.0),
+            )
+        }
+        quickcheck(prop as fn(Cuboid, Cuboid) -> TestResult);
+    }
+
+    #[test]
+    fn clamp_inside_bounds() {
+        fn prop(a: Cuboid, b: Cuboid) -> bool {
+            let clamped = a.0.clamp(&b.0);
+            is_inside(&clamped, &b.0)
+                && (!a.0.overlaps(&b.0) || Some(clamped.clone()) == a.0.intersection(&b.0))
+                && clamped.clamp(&b.0) == clamped
+        }
+        quickcheck(prop as fn(Cuboid, Cuboid) -> bool);
+    }
+}
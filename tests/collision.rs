@@ -0,0 +1,226 @@
+mod common;
+
+#[cfg(test)]
+mod raycast {
+    use bevy::math::{IVec3, Vec3};
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::{Aabb3D, Axis},
+        optimise_world::SubChunk,
+    };
+
+    use crate::common::empty_source;
+
+    #[test]
+    fn down_onto_floor() {
+        let mut source = empty_source();
+        source[2][3][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let hit = sub_chunk
+            .raycast(Vec3::new(3.5, 5.0, 2.5), Vec3::NEG_Y, 10.0)
+            .expect("Ray should hit the floor");
+        assert_eq!(IVec3::new(3, 0, 2), hit.pos);
+        assert_eq!(Axis::Y, hit.face);
+        assert!((hit.distance - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wall() {
+        let mut source = empty_source();
+        source[0][5][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let hit = sub_chunk
+            .raycast(Vec3::new(0.5, 1.5, 0.5), Vec3::X, 16.0)
+            .expect("Ray should hit the wall");
+        assert_eq!(IVec3::new(5, 1, 0), hit.pos);
+        assert_eq!(Axis::NegX, hit.face);
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn max_dist() {
+        let mut source = empty_source();
+        source[0][5][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        assert!(sub_chunk
+            .raycast(Vec3::new(0.5, 1.5, 0.5), Vec3::X, 4.0)
+            .is_none());
+    }
+
+    #[test]
+    fn miss() {
+        let mut source = empty_source();
+        source[0][5][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        assert!(sub_chunk
+            .raycast(Vec3::new(0.5, 2.5, 0.5), Vec3::X, 16.0)
+            .is_none());
+        assert!(sub_chunk
+            .raycast(Vec3::new(0.5, 1.5, 0.5), Vec3::ZERO, 16.0)
+            .is_none());
+    }
+
+    #[test]
+    fn slab_through_empty_space() {
+        let mut source = empty_source();
+        source[4][4][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        // Passes over the slab's cell in the top half, then hits it from above
+        let dir = Vec3::new(1.0, -1.0, 1.0);
+        let hit = sub_chunk
+            .raycast(Vec3::new(1.5, 3.5, 1.5), dir, 16.0)
+            .expect("Ray should hit the slab");
+        assert_eq!(IVec3::new(4, 0, 4), hit.pos);
+        assert_eq!(Axis::Y, hit.face);
+        assert!((hit.distance - 3.0 * 3f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn tall_fence() {
+        let mut source = empty_source();
+        source[0][3][0] = smallvec![Aabb3D([0.375, 0.0, 0.375, 0.625, 1.5, 0.625])];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let hit = sub_chunk
+            .raycast(Vec3::new(0.5, 1.25, 0.5), Vec3::X, 16.0)
+            .expect("Ray should hit the top of the fence");
+        assert_eq!(IVec3::new(3, 0, 0), hit.pos);
+        assert_eq!(Axis::NegX, hit.face);
+        assert!((hit.distance - 2.875).abs() < 1e-4);
+    }
+
+    #[test]
+    fn world_coordinates() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::new(1, 4, -1), source);
+
+        // Starts outside the sub chunk
+        let hit = sub_chunk
+            .raycast(Vec3::new(16.5, 80.0, -15.5), Vec3::NEG_Y, 32.0)
+            .expect("Ray should hit the block");
+        assert_eq!(IVec3::new(16, 64, -16), hit.pos);
+        assert_eq!(Axis::Y, hit.face);
+        assert!((hit.distance - 15.0).abs() < 1e-4);
+    }
+}
+
+#[cfg(test)]
+mod sweep {
+    use bevy::math::{IVec3, Vec3};
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::{Aabb3D, Axis},
+        optimise_world::{SubChunk, SubChunkHalo, SubChunkSource, CHUNK_WIDTH},
+    };
+
+    use crate::common::empty_source;
+
+    fn player(x: f32, y: f32, z: f32) -> Aabb3D {
+        Aabb3D([x - 0.3, y, z - 0.3, x + 0.3, y + 1.8, z + 0.3])
+    }
+
+    fn floor() -> SubChunkSource {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        source
+    }
+
+    #[test]
+    fn fall_onto_floor() {
+        let sub_chunk = SubChunk::new(IVec3::ZERO, floor());
+
+        let hit = sub_chunk
+            .sweep(&player(4.5, 3.0, 4.5), Vec3::new(0.0, -4.0, 0.0))
+            .expect("Player should land on the floor");
+        assert!((hit.time - 0.5).abs() < 1e-4);
+        assert_eq!(Axis::Y, hit.normal);
+        assert_eq!(0, hit.pos.y);
+    }
+
+    #[test]
+    fn slide_along_floor() {
+        let sub_chunk = SubChunk::new(IVec3::ZERO, floor());
+
+        assert!(sub_chunk
+            .sweep(&player(4.5, 1.0, 4.5), Vec3::new(3.0, 0.0, 2.0))
+            .is_none());
+    }
+
+    #[test]
+    fn walk_into_wall() {
+        let mut source = floor();
+        source[4][8][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let hit = sub_chunk
+            .sweep(&player(4.5, 1.0, 4.5), Vec3::new(4.0, 0.0, 0.0))
+            .expect("Player should hit the wall");
+        assert!((hit.time - 0.8).abs() < 1e-4);
+        assert_eq!(Axis::NegX, hit.normal);
+        assert_eq!(IVec3::new(8, 1, 4), hit.pos);
+    }
+
+    #[test]
+    fn touching_wall() {
+        let mut source = floor();
+        source[4][8][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let hit = sub_chunk
+            .sweep(&player(7.7, 1.0, 4.5), Vec3::new(1.0, 0.0, 0.0))
+            .expect("Player should already be against the wall");
+        assert_eq!(0.0, hit.time);
+
+        assert!(sub_chunk
+            .sweep(&player(7.7, 1.0, 4.5), Vec3::new(-1.0, 0.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn fence_blocks_jump_over() {
+        let mut source = floor();
+        source[4][8][1] = smallvec![Aabb3D([0.375, 0.0, 0.375, 0.625, 1.5, 0.625])];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        // Clears a block, but not the fence
+        let hit = sub_chunk
+            .sweep(&player(6.5, 2.2, 4.5), Vec3::new(4.0, 0.0, 0.0))
+            .expect("Player should hit the top of the fence");
+        assert_eq!(Axis::NegX, hit.normal);
+
+        assert!(sub_chunk
+            .sweep(&player(6.5, 2.6, 4.5), Vec3::new(4.0, 0.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn halo_and_world_coordinates() {
+        let mut halo = SubChunkHalo::default();
+        halo.insert(IVec3::new(4, -1, 4), smallvec![Aabb3D::FULL_BLOCK]);
+        let sub_chunk = SubChunk::new_with_halo(IVec3::new(1, 4, -1), empty_source(), halo);
+
+        let hit = sub_chunk
+            .sweep(&player(20.5, 66.0, -11.5), Vec3::new(0.0, -4.0, 0.0))
+            .expect("Player should land on the halo block");
+        assert!((hit.time - 0.5).abs() < 1e-4);
+        assert_eq!(IVec3::new(20, 63, -12), hit.pos);
+    }
+
+    #[test]
+    fn no_motion() {
+        let sub_chunk = SubChunk::new(IVec3::ZERO, floor());
+        assert!(sub_chunk
+            .sweep(&player(4.5, 1.0, 4.5), Vec3::ZERO)
+            .is_none());
+    }
+}
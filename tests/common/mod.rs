@@ -0,0 +1,8 @@
+//! Helpers shared by the integration tests
+
+use wallace::aabb::optimise_world::SubChunkSource;
+
+/// Sub chunk source without any collision boxes
+pub fn empty_source() -> SubChunkSource {
+    Default::default()
+}
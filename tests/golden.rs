@@ -0,0 +1,298 @@
+//! Nav meshes of small hand built sub chunks, compared against the files in `tests/golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to regenerate the files after an intended change to nav mesh
+//! generation, then review the diff.
+
+mod common;
+
+#[cfg(test)]
+mod golden {
+    use std::{fmt::Write, fs, path::PathBuf};
+
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{
+            NavMeshAdjacent, NavMeshLayer, SubChunk, SubChunkNavMesh, SubChunkSource,
+        },
+    };
+
+    use crate::common::empty_source;
+
+    const FLOOR_SIZE: usize = 7;
+
+    const BOTTOM_SLAB: Aabb3D = Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0]);
+    const TOP_SLAB: Aabb3D = Aabb3D([0.0, 0.5, 0.0, 1.0, 1.0, 1.0]);
+    const CARPET: Aabb3D = Aabb3D([0.0, 0.0, 0.0, 1.0, 0.0625, 1.0]);
+    /// Fence post, extended by the arms towards connected neighbours
+    const FENCE_POST: Aabb3D = Aabb3D([0.375, 0.0, 0.375, 0.625, 1.5, 0.625]);
+    const FENCE_ARM_MIN_X: Aabb3D = Aabb3D([0.0, 0.0, 0.375, 0.375, 1.5, 0.625]);
+    const FENCE_ARM_MAX_X: Aabb3D = Aabb3D([0.625, 0.0, 0.375, 1.0, 1.5, 0.625]);
+    /// Closed fence gate facing along z, open gates have no collision
+    const FENCE_GATE: Aabb3D = Aabb3D([0.0, 0.0, 0.375, 1.0, 1.5, 0.625]);
+    const LANTERN: [Aabb3D; 2] = [
+        Aabb3D([0.3125, 0.0, 0.3125, 0.6875, 0.4375, 0.6875]),
+        Aabb3D([0.375, 0.4375, 0.375, 0.625, 0.5625, 0.625]),
+    ];
+    const HANGING_LANTERN: [Aabb3D; 2] = [
+        Aabb3D([0.3125, 0.0625, 0.3125, 0.6875, 0.5, 0.6875]),
+        Aabb3D([0.375, 0.5, 0.375, 0.625, 0.625, 0.625]),
+    ];
+
+    /// Full blocks covering the corner of the sub chunk at y = 0
+    fn floor() -> SubChunkSource {
+        let mut source = empty_source();
+        for plane in source.iter_mut().take(FLOOR_SIZE) {
+            for column in plane.iter_mut().take(FLOOR_SIZE) {
+                column[0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        source
+    }
+
+    /// Straight fence along x at `z`, with `gate` at `gate_x`
+    fn fence(source: &mut SubChunkSource, z: usize, gate_x: usize, gate: Option<Aabb3D>) {
+        for x in 0..FLOOR_SIZE {
+            source[z][x][1] = if x == gate_x {
+                gate.iter().cloned().collect()
+            } else {
+                let mut block = smallvec![FENCE_POST];
+                if x > 0 {
+                    block.push(FENCE_ARM_MIN_X);
+                }
+                if x < FLOOR_SIZE - 1 {
+                    block.push(FENCE_ARM_MAX_X);
+                }
+                block
+            };
+        }
+    }
+
+    fn stairs() -> SubChunkSource {
+        let mut source = floor();
+        // Stairs rising along x, each a bottom slab with a raised back half
+        for z in 2..5 {
+            for (step, x) in (2..5).enumerate() {
+                for y in 1..=step {
+                    source[z][x][y] = smallvec![Aabb3D::FULL_BLOCK];
+                }
+                source[z][x][step + 1] =
+                    smallvec![BOTTOM_SLAB, Aabb3D([0.5, 0.5, 0.0, 1.0, 1.0, 1.0])];
+            }
+        }
+        source
+    }
+
+    fn slabs() -> SubChunkSource {
+        let mut source = floor();
+        for z in 0..FLOOR_SIZE {
+            source[z][2][1] = smallvec![BOTTOM_SLAB];
+            source[z][4][1] = smallvec![Aabb3D::FULL_BLOCK];
+            source[z][4][2] = smallvec![BOTTOM_SLAB];
+        }
+        // Top slab with head room to walk beneath it
+        source[3][0][3] = smallvec![TOP_SLAB];
+        source
+    }
+
+    fn fences() -> SubChunkSource {
+        let mut source = floor();
+        fence(&mut source, 3, FLOOR_SIZE, None);
+        source
+    }
+
+    fn fence_gate_closed() -> SubChunkSource {
+        let mut source = floor();
+        fence(&mut source, 3, 3, Some(FENCE_GATE));
+        source
+    }
+
+    fn fence_gate_open() -> SubChunkSource {
+        let mut source = floor();
+        fence(&mut source, 3, 3, None);
+        source
+    }
+
+    fn lanterns() -> SubChunkSource {
+        let mut source = floor();
+        source[1][1][1] = LANTERN.into_iter().collect();
+        // Hanging from a block with head room below
+        source[4][4][4] = smallvec![Aabb3D::FULL_BLOCK];
+        source[4][4][3] = HANGING_LANTERN.into_iter().collect();
+        source
+    }
+
+    fn carpets() -> SubChunkSource {
+        let mut source = floor();
+        for z in 1..4 {
+            for x in 1..4 {
+                source[z][x][1] = smallvec![CARPET];
+            }
+        }
+        // Carpet on top of a full block
+        source[5][5][1] = smallvec![Aabb3D::FULL_BLOCK];
+        source[5][5][2] = smallvec![CARPET];
+        source
+    }
+
+    fn doorway() -> SubChunkSource {
+        let mut source = floor();
+        // Wall along z at x = 3 with a two block high opening at z = 3
+        for z in 0..FLOOR_SIZE {
+            for y in 1..4 {
+                if z != 3 || y == 3 {
+                    source[z][3][y] = smallvec![Aabb3D::FULL_BLOCK];
+                }
+            }
+        }
+        source
+    }
+
+    fn number(value: f32) -> String {
+        let text = format!("{:.3}", value);
+        if text == "-0.000" {
+            "0.000".to_string()
+        } else {
+            text
+        }
+    }
+
+    fn describe_layer(out: &mut String, kind: &str, index: usize, layer: &NavMeshLayer) {
+        writeln!(out, "{} {} height {}", kind, index, number(layer.height)).unwrap();
+        for (node_index, node) in layer.nodes.iter().enumerate() {
+            let aabb = node.layer_aabb();
+            writeln!(
+                out,
+                "  node {} [{}, {}] x {}..{} z {}..{}",
+                node_index,
+                node.pos.x,
+                node.pos.y,
+                number(aabb.min_x),
+                number(aabb.max_x),
+                number(aabb.min_y),
+                number(aabb.max_y)
+            )
+            .unwrap();
+            for adjacent in node.adjacent.iter() {
+                let (kind, min, max, axis) = match adjacent {
+                    NavMeshAdjacent::Superset { min, max, axis, .. } => {
+                        ("superset", min, max, axis)
+                    }
+                    NavMeshAdjacent::Subset { min, max, axis, .. } => ("subset", min, max, axis),
+                    NavMeshAdjacent::Overlapping { min, max, axis, .. } => {
+                        ("overlapping", min, max, axis)
+                    }
+                };
+                writeln!(
+                    out,
+                    "    adjacent {} {} axis {} {}..{}",
+                    kind,
+                    adjacent.index(),
+                    axis,
+                    number(*min),
+                    number(*max)
+                )
+                .unwrap();
+            }
+            for link in node.links.iter() {
+                writeln!(
+                    out,
+                    "    link {:?} to {}:{} cost {} axis {} at {} {}..{}",
+                    link.link_type,
+                    link.to.layer,
+                    link.to.node,
+                    number(link.cost),
+                    link.axis,
+                    number(link.value),
+                    number(link.min),
+                    number(link.max)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn describe(nav: &SubChunkNavMesh) -> String {
+        let mut out = String::new();
+        for (index, layer) in nav.floor.iter().enumerate() {
+            describe_layer(&mut out, "floor", index, layer);
+        }
+        for (index, layer) in nav.ceiling.iter().enumerate() {
+            describe_layer(&mut out, "ceiling", index, layer);
+        }
+        out
+    }
+
+    fn check(name: &str, source: SubChunkSource) {
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(Vec::<String>::new(), nav.validate(), "{} is invalid", name);
+        let actual = describe(&nav);
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.txt", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+            panic!(
+                "Can't read {}: {}, run with UPDATE_GOLDEN=1 to create it",
+                path.display(),
+                err
+            )
+        });
+        assert!(
+            expected == actual,
+            "{} doesn't match {}, run with UPDATE_GOLDEN=1 to update it\n\nexpected:\n{}\nactual:\n{}",
+            name,
+            path.display(),
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn golden_stairs() {
+        check("stairs", stairs());
+    }
+
+    #[test]
+    fn golden_slabs() {
+        check("slabs", slabs());
+    }
+
+    #[test]
+    fn golden_fences() {
+        check("fences", fences());
+    }
+
+    #[test]
+    fn golden_fence_gate_closed() {
+        check("fence_gate_closed", fence_gate_closed());
+    }
+
+    #[test]
+    fn golden_fence_gate_open() {
+        check("fence_gate_open", fence_gate_open());
+    }
+
+    #[test]
+    fn golden_lanterns() {
+        check("lanterns", lanterns());
+    }
+
+    #[test]
+    fn golden_carpets() {
+        check("carpets", carpets());
+    }
+
+    #[test]
+    fn golden_doorway() {
+        check("doorway", doorway());
+    }
+}
@@ -0,0 +1,184 @@
+mod common;
+
+#[cfg(test)]
+mod nav_build {
+    use std::time::{Duration, Instant};
+
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        nav_build::NavBuildTasks,
+        nav_world::NavWorld,
+        optimise_world::{SubChunk, CHUNK_WIDTH},
+        serialise::SubChunkData,
+    };
+
+    use crate::common::empty_source;
+
+    /// Floor of full blocks with a slab step
+    fn floor(location: IVec3) -> SubChunk {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        source[4][4][1] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        SubChunk::new(location, source)
+    }
+
+    /// Poll until every task has finished, returning the indices of the finished sub chunks
+    fn wait(tasks: &mut NavBuildTasks) -> Vec<IVec3> {
+        let start = Instant::now();
+        let mut built = vec![];
+        while !tasks.is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "Tasks didn't finish"
+            );
+            built.extend(tasks.poll());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        built
+    }
+
+    #[test]
+    fn matches_synchronous_build() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        let sub_chunk = floor(IVec3::new(1, 2, 3));
+        tasks.spawn(SubChunkData::new(&sub_chunk));
+        assert!(tasks.contains(IVec3::new(1, 2, 3)));
+
+        assert_eq!(vec![IVec3::new(1, 2, 3)], wait(&mut tasks));
+        let built = tasks
+            .take_built(IVec3::new(1, 2, 3))
+            .expect("Sub chunk should be built");
+        assert_eq!(IVec3::new(1, 2, 3), built.sub_chunk.location);
+        assert!(!tasks.contains(IVec3::new(1, 2, 3)));
+
+        assert_eq!(
+            sub_chunk.build_nav_mesh(&AgentParams::PLAYER),
+            built.nav_mesh
+        );
+    }
+
+    #[test]
+    fn many_sub_chunks() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        for x in 0..8 {
+            tasks.spawn(SubChunkData::new(&floor(IVec3::new(x, 0, 0))));
+        }
+        assert_eq!(8, tasks.len());
+
+        let mut nav_world = NavWorld::new(tasks.agent().clone());
+        for index in wait(&mut tasks) {
+            assert!(tasks.insert_built(&mut nav_world, index));
+        }
+        assert_eq!(8, nav_world.len());
+        assert!((0..8).all(|x| nav_world.contains(IVec3::new(x, 0, 0))));
+        assert!(!tasks.insert_built(&mut nav_world, IVec3::ZERO));
+    }
+
+    #[test]
+    fn respawn_replaces_task() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        assert_eq!(1, tasks.len());
+        assert_eq!(1, wait(&mut tasks).len());
+    }
+
+    #[test]
+    fn cancel() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        assert!(tasks.cancel(IVec3::ZERO));
+        assert!(!tasks.cancel(IVec3::ZERO));
+        assert!(tasks.is_empty());
+        assert!(tasks.poll().is_empty());
+    }
+
+    #[test]
+    fn dirtied_while_building() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        let mut nav_world = NavWorld::new(tasks.agent().clone());
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        tasks.spawn(SubChunkData::new(&floor(IVec3::X)));
+
+        tasks.mark_dirty(IVec3::ZERO);
+        tasks.mark_dirty(IVec3::Y);
+        assert!(tasks.is_dirty(IVec3::ZERO));
+        assert!(!tasks.is_dirty(IVec3::Y));
+
+        for index in wait(&mut tasks) {
+            tasks.insert_built(&mut nav_world, index);
+        }
+        assert!(nav_world.is_dirty(IVec3::ZERO));
+        assert!(!nav_world.is_dirty(IVec3::X));
+    }
+
+    #[test]
+    fn dirtied_between_poll_and_insert() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        let mut nav_world = NavWorld::new(tasks.agent().clone());
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        assert_eq!(vec![IVec3::ZERO], wait(&mut tasks));
+
+        // Not in the nav world yet, so only the build tasks can keep track of the change
+        nav_world.mark_dirty(IVec3::ZERO);
+        tasks.mark_dirty(IVec3::ZERO);
+        assert!(tasks.insert_built(&mut nav_world, IVec3::ZERO));
+        assert!(nav_world.is_dirty(IVec3::ZERO));
+    }
+
+    #[test]
+    fn respawn_clears_dirty() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        let mut nav_world = NavWorld::new(tasks.agent().clone());
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        tasks.mark_dirty(IVec3::ZERO);
+
+        // Copied again after the change
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        assert!(!tasks.is_dirty(IVec3::ZERO));
+        for index in wait(&mut tasks) {
+            tasks.insert_built(&mut nav_world, index);
+        }
+        assert!(!nav_world.is_dirty(IVec3::ZERO));
+    }
+
+    #[test]
+    fn evict_far_tasks() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        let mut nav_world = NavWorld::new(tasks.agent().clone());
+        tasks.spawn(SubChunkData::new(&floor(IVec3::ZERO)));
+        tasks.spawn(SubChunkData::new(&floor(IVec3::new(5, 0, 0))));
+
+        assert_eq!(
+            vec![IVec3::new(5, 0, 0)],
+            tasks.evict(&[IVec3::ZERO], IVec3::ONE)
+        );
+        assert!(tasks.contains(IVec3::ZERO));
+        assert!(!tasks.contains(IVec3::new(5, 0, 0)));
+
+        assert_eq!(vec![IVec3::ZERO], wait(&mut tasks));
+        assert!(tasks.insert_built(&mut nav_world, IVec3::ZERO));
+        assert!(!nav_world.contains(IVec3::new(5, 0, 0)));
+    }
+
+    #[test]
+    fn evict_far_built() {
+        let mut tasks = NavBuildTasks::new(AgentParams::PLAYER);
+        let mut nav_world = NavWorld::new(tasks.agent().clone());
+        tasks.spawn(SubChunkData::new(&floor(IVec3::new(5, 0, 0))));
+        wait(&mut tasks);
+
+        assert_eq!(
+            vec![IVec3::new(5, 0, 0)],
+            tasks.evict(&[IVec3::ZERO], IVec3::ONE)
+        );
+        assert!(!tasks.insert_built(&mut nav_world, IVec3::new(5, 0, 0)));
+    }
+}
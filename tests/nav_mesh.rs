@@ -0,0 +1,566 @@
+mod common;
+
+#[cfg(test)]
+mod nav_mesh_adjacent {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_2d::Aabb2D,
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{NavMeshAdjacent, SubChunk},
+    };
+
+    use crate::common::empty_source;
+
+    #[test]
+    fn neighbouring_blocks() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let nodes = &nav.floor[0].nodes;
+        assert_eq!(1, nodes.len());
+        assert!(nodes[0].adjacent.is_empty());
+        assert_eq!(
+            Aabb2D {
+                min_x: -0.3,
+                min_y: -0.3,
+                max_x: 2.3,
+                max_y: 1.3
+            },
+            nodes[0].layer_aabb()
+        );
+    }
+
+    #[test]
+    fn shared_edge() {
+        let a = Aabb2D {
+            min_x: -0.3,
+            min_y: -0.3,
+            max_x: 1.3,
+            max_y: 1.3,
+        };
+        let b = Aabb2D {
+            min_x: 1.3,
+            min_y: -0.3,
+            max_x: 2.3,
+            max_y: 1.3,
+        };
+
+        let (a_adjacent, b_adjacent) = NavMeshAdjacent::between(&a, 0, &b, 1).unwrap();
+        assert!(matches!(a_adjacent, NavMeshAdjacent::Superset { .. }));
+        assert_eq!(1, a_adjacent.index());
+        assert_eq!(2, a_adjacent.axis());
+        assert_eq!((-0.3, 1.3), a_adjacent.interval());
+
+        assert!(matches!(b_adjacent, NavMeshAdjacent::Superset { .. }));
+        assert_eq!(0, b_adjacent.index());
+        assert_eq!(0, b_adjacent.axis());
+    }
+
+    #[test]
+    fn partial_edge() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 1.0, 0.5])];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let nodes = &nav.floor[0].nodes;
+        let (a, b) = if nodes[0].pos.x == 0 { (0, 1) } else { (1, 0) };
+
+        let adjacent = &nodes[a].adjacent[0];
+        assert!(matches!(adjacent, NavMeshAdjacent::Superset { .. }));
+        assert_eq!((-0.3, 0.8), adjacent.interval());
+
+        assert!(matches!(
+            &nodes[b].adjacent[0],
+            NavMeshAdjacent::Subset { .. }
+        ));
+    }
+
+    #[test]
+    fn separate_blocks() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][3][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav.floor[0]
+            .nodes
+            .iter()
+            .all(|node| node.adjacent.is_empty()));
+    }
+
+    #[test]
+    fn different_layers() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(2, nav.floor.len());
+        for layer in nav.floor.iter() {
+            assert!(layer.nodes.iter().all(|node| node.adjacent.is_empty()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod nav_mesh_links {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_2d::Aabb2D,
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{NavMeshLink, NavMeshLinkType, SubChunk, SubChunkNavMesh, SubChunkSource},
+    };
+
+    use crate::common::empty_source;
+
+    fn link_types(nav: &SubChunkNavMesh, height: f32) -> Vec<NavMeshLinkType> {
+        nav.floor
+            .iter()
+            .filter(|layer| layer.height == height)
+            .flat_map(|layer| layer.nodes.iter())
+            .flat_map(|node| node.links.iter())
+            .map(|link| link.link_type)
+            .collect()
+    }
+
+    #[test]
+    fn step_up_slab() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][1] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(vec![NavMeshLinkType::StepUp], link_types(&nav, 1.0));
+        assert_eq!(vec![NavMeshLinkType::Drop], link_types(&nav, 1.5));
+    }
+
+    #[test]
+    fn jump_up_block() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][1] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(vec![NavMeshLinkType::JumpUp], link_types(&nav, 1.0));
+        assert_eq!(vec![NavMeshLinkType::Drop], link_types(&nav, 2.0));
+    }
+
+    #[test]
+    fn jump_up_blocked_by_ceiling() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][1] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][0][3] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(link_types(&nav, 1.0).is_empty());
+    }
+
+    #[test]
+    fn gap_jump() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][2][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(
+            vec![NavMeshLinkType::GapJump, NavMeshLinkType::GapJump],
+            link_types(&nav, 1.0)
+        );
+    }
+
+    #[test]
+    fn gap_too_wide() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][4][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(link_types(&nav, 1.0).is_empty());
+    }
+
+    #[test]
+    fn drop_too_far() {
+        let mut source = empty_source();
+        source[0][0][4] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(link_types(&nav, 5.0).is_empty());
+        assert!(link_types(&nav, 1.0).is_empty());
+    }
+
+    /// Ground with a single block platform above it, the ground continues under the platform
+    fn platform() -> SubChunkSource {
+        let mut source = empty_source();
+        for z in 0..6 {
+            for x in 0..6 {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        source[2][2][3] = smallvec![Aabb3D::FULL_BLOCK];
+        source
+    }
+
+    fn drops_to(nav: &SubChunkNavMesh, from: f32, to: f32) -> Vec<(Aabb2D, NavMeshLink)> {
+        let to = nav.floor.iter().position(|layer| layer.height == to);
+        nav.floor
+            .iter()
+            .filter(|layer| layer.height == from)
+            .flat_map(|layer| layer.nodes.iter())
+            .flat_map(|node| {
+                node.links
+                    .iter()
+                    .map(|link| (node.layer_aabb(), link.clone()))
+            })
+            .filter(|(_, link)| Some(link.to.layer) == to)
+            .collect()
+    }
+
+    #[test]
+    fn drop_from_edge() {
+        let nav = SubChunk::new(IVec3::ZERO, platform()).build_nav_mesh(&AgentParams::PLAYER);
+        let drops = drops_to(&nav, 4.0, 1.0);
+        assert!(!drops.is_empty());
+        for (from, link) in drops {
+            assert_eq!(NavMeshLinkType::Drop, link.link_type);
+            let edge = from.to_array()[link.axis as usize / 2][link.axis as usize % 2];
+            assert!(
+                (link.value - edge).abs() < 1e-4,
+                "{:?} doesn't cross the edge of {:?}",
+                link,
+                from
+            );
+        }
+    }
+
+    #[test]
+    fn drop_blocked_beside_edge() {
+        let mut source = platform();
+        source[2][3][3] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(drops_to(&nav, 4.0, 1.0).is_empty());
+        assert_eq!(vec![NavMeshLinkType::Drop], link_types(&nav, 4.0));
+    }
+}
+
+#[cfg(test)]
+mod nav_mesh_agent {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{SubChunk, SubChunkHalo},
+    };
+
+    use crate::common::empty_source;
+
+    fn low_ceiling() -> SubChunk {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][0][2] = smallvec![Aabb3D([0.0, 0.5, 0.0, 1.0, 1.0, 1.0])];
+        SubChunk::new(IVec3::ZERO, source)
+    }
+
+    #[test]
+    fn sneaking_headroom() {
+        let sub_chunk = low_ceiling();
+
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav
+            .floor
+            .iter()
+            .all(|layer| layer.height != 1.0 || layer.nodes.is_empty()));
+
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER.sneaking());
+        assert!(nav
+            .floor
+            .iter()
+            .any(|layer| layer.height == 1.0 && !layer.nodes.is_empty()));
+    }
+
+    #[test]
+    fn radius() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let agent = AgentParams {
+            radius: 0.5,
+            ..AgentParams::PLAYER
+        };
+        let nav = sub_chunk.build_nav_mesh(&agent);
+        assert_eq!(-0.5, nav.floor[0].nodes[0].aabb.min_x);
+        assert_eq!(1.5, nav.floor[0].nodes[0].aabb.max_x);
+    }
+
+    #[test]
+    fn tall_agent_headroom() {
+        let agent = AgentParams {
+            height: 2.5,
+            ..AgentParams::PLAYER
+        };
+        let mut source = empty_source();
+        source[0][0][15] = smallvec![Aabb3D::FULL_BLOCK];
+        let mut halo = SubChunkHalo::new(&agent);
+        halo.insert(IVec3::new(0, 18, 0), smallvec![Aabb3D::FULL_BLOCK]);
+        let sub_chunk = SubChunk::new_with_halo(IVec3::ZERO, source, halo);
+
+        let nav = sub_chunk.build_nav_mesh(&agent);
+        assert!(nav
+            .floor
+            .iter()
+            .all(|layer| layer.height != 16.0 || layer.nodes.is_empty()));
+
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav
+            .floor
+            .iter()
+            .any(|layer| layer.height == 16.0 && !layer.nodes.is_empty()));
+    }
+
+    #[test]
+    fn wide_agent_wall_in_halo() {
+        let agent = AgentParams {
+            radius: 0.7,
+            ..AgentParams::PLAYER
+        };
+        let mut source = empty_source();
+        source[5][15][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let mut halo = SubChunkHalo::new(&agent);
+        halo.insert(IVec3::new(17, 1, 5), smallvec![Aabb3D::FULL_BLOCK]);
+        halo.insert(IVec3::new(17, 2, 5), smallvec![Aabb3D::FULL_BLOCK]);
+        let sub_chunk = SubChunk::new_with_halo(IVec3::ZERO, source, halo);
+
+        // The wall two blocks away is within reach of the inflated node
+        let nav = sub_chunk.build_nav_mesh(&agent);
+        let layer = nav.floor.iter().find(|layer| layer.height == 1.0).unwrap();
+        assert_eq!(1, layer.nodes.len());
+        assert!((layer.nodes[0].aabb.max_x - 1.3).abs() < 1e-5);
+        assert!((layer.nodes[0].aabb.min_x + 0.7).abs() < 1e-5);
+
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER);
+        let layer = nav.floor.iter().find(|layer| layer.height == 1.0).unwrap();
+        assert!((layer.nodes[0].aabb.max_x - 1.3).abs() < 1e-5);
+    }
+}
+
+#[cfg(test)]
+mod nav_mesh_overlap {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_2d::Aabb2D,
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{NavMeshLayer, SubChunk, CHUNK_WIDTH},
+    };
+
+    use crate::common::empty_source;
+
+    fn assert_disjoint(layer: &NavMeshLayer) {
+        let aabbs: Vec<Aabb2D> = layer.nodes.iter().map(|node| node.layer_aabb()).collect();
+        for a in 0..aabbs.len() {
+            for b in a + 1..aabbs.len() {
+                let overlap_x =
+                    aabbs[a].max_x.min(aabbs[b].max_x) - aabbs[a].min_x.max(aabbs[b].min_x);
+                let overlap_y =
+                    aabbs[a].max_y.min(aabbs[b].max_y) - aabbs[a].min_y.max(aabbs[b].min_y);
+                assert!(
+                    overlap_x <= 1e-4 || overlap_y <= 1e-4,
+                    "{:?} overlaps {:?}",
+                    aabbs[a],
+                    aabbs[b]
+                );
+            }
+        }
+    }
+
+    fn area(layer: &NavMeshLayer) -> f32 {
+        layer.nodes.iter().map(|node| node.aabb.area()).sum()
+    }
+
+    #[test]
+    fn row_of_blocks() {
+        let mut source = empty_source();
+        for x in 0..3 {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let layer = &nav.floor[0];
+        assert_disjoint(layer);
+        assert!((area(layer) - 3.6 * 1.6).abs() < 1e-3);
+    }
+
+    #[test]
+    fn full_layer() {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let layer = &nav.floor[0];
+        assert_disjoint(layer);
+        assert!((area(layer) - 16.6 * 16.6).abs() < 1e-2);
+        assert_eq!(1, layer.nodes.len());
+    }
+
+    #[test]
+    fn block_index_covers_merged_nodes() {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let layer = &nav.floor[0];
+        for block in layer.blocks.iter().flatten() {
+            assert_eq!(&[0], block.as_slice());
+        }
+    }
+
+    #[test]
+    fn l_shape() {
+        let mut source = empty_source();
+        for x in 0..4 {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        for z in 1..4 {
+            source[z][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let layer = &nav.floor[0];
+        assert_disjoint(layer);
+        assert!((area(layer) - (4.6 * 1.6 + 1.6 * 3.0)).abs() < 1e-3);
+        assert!(layer.nodes.len() <= 3);
+    }
+
+    #[test]
+    fn slab_beside_block() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][1][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 1.0, 0.5])];
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        let layer = &nav.floor[0];
+        assert_disjoint(layer);
+        assert!((area(layer) - (1.6 * 1.6 + 1.0 * 1.1)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn slabs_leave_no_slivers() {
+        // Nearly coincident edges of the inflated slab and block nodes leave float noise behind
+        // when subtracted from each other
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+                if (x * 7 + z * 3) % 5 == 0 {
+                    source[z][x][1] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+                }
+            }
+        }
+
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        for layer in nav.floor.iter() {
+            assert_disjoint(layer);
+            for node in layer.nodes.iter() {
+                let aabb = &node.aabb;
+                assert!(
+                    aabb.max_x - aabb.min_x > 1e-4 && aabb.max_y - aabb.min_y > 1e-4,
+                    "Sliver node {:?} at {}",
+                    aabb,
+                    node.pos
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod nav_mesh_validate {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{NavNodeId, SubChunk, CHUNK_WIDTH},
+    };
+
+    use crate::common::empty_source;
+
+    fn stairs() -> SubChunk {
+        let mut source = empty_source();
+        for x in 0..6 {
+            for z in 0..3 {
+                for y in 0..=x / 2 {
+                    source[z][x][y] = smallvec![Aabb3D::FULL_BLOCK];
+                }
+            }
+        }
+        source[1][3][2] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        SubChunk::new(IVec3::ZERO, source)
+    }
+
+    #[test]
+    fn valid() {
+        let nav = stairs().build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(Vec::<String>::new(), nav.validate());
+    }
+
+    #[test]
+    fn slabs_on_floor() {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+                if (x * 7 + z * 3) % 5 == 0 {
+                    source[z][x][1] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+                }
+            }
+        }
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(Vec::<String>::new(), nav.validate());
+    }
+
+    #[test]
+    fn missing_link_target() {
+        let mut nav = stairs().build_nav_mesh(&AgentParams::PLAYER);
+        let node = nav.floor[0]
+            .nodes
+            .iter_mut()
+            .find(|node| !node.links.is_empty())
+            .expect("Stairs should have links");
+        node.links[0].to = NavNodeId {
+            layer: 100,
+            node: 0,
+        };
+        assert_eq!(1, nav.validate().len());
+    }
+
+    #[test]
+    fn unsorted_layers() {
+        let mut nav = stairs().build_nav_mesh(&AgentParams::PLAYER);
+        nav.floor.swap(0, 1);
+        assert!(!nav.validate().is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+mod common;
+
+#[cfg(test)]
+mod nav_world {
+    use bevy::math::{IVec3, Vec3};
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        nav_world::{NavSubChunk, NavWorld},
+        optimise_world::{NavMeshLinkType, SubChunk, CHUNK_WIDTH},
+        pathfind::find_path,
+    };
+
+    use crate::common::empty_source;
+
+    /// Row of blocks along the x axis at the bottom of a sub chunk
+    fn row(location: IVec3) -> SubChunk {
+        let mut source = empty_source();
+        for x in 0..CHUNK_WIDTH {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        SubChunk::new(location, source)
+    }
+
+    #[test]
+    fn sub_chunk_index() {
+        assert_eq!(
+            IVec3::ZERO,
+            NavWorld::sub_chunk_index(Vec3::new(0.5, 15.9, 0.0))
+        );
+        assert_eq!(
+            IVec3::new(-1, 4, 1),
+            NavWorld::sub_chunk_index(Vec3::new(-0.5, 64.0, 16.0))
+        );
+    }
+
+    #[test]
+    fn lazy_insert() {
+        let mut nav_world = NavWorld::default();
+        assert!(nav_world.get_or_insert_with(IVec3::ZERO, || None).is_none());
+        assert!(nav_world.is_empty());
+
+        assert!(nav_world
+            .get_or_insert_with(IVec3::ZERO, || Some(row(IVec3::ZERO)))
+            .is_some());
+        assert!(nav_world
+            .get_or_insert_with(IVec3::ZERO, || panic!("Sub chunk should already be built"))
+            .is_some());
+        assert_eq!(1, nav_world.len());
+    }
+
+    #[test]
+    fn node_at() {
+        let mut nav_world = NavWorld::default();
+        nav_world.insert(row(IVec3::new(0, 4, 0)));
+        nav_world.insert(row(IVec3::new(1, 4, 0)));
+
+        let node = nav_world
+            .node_at(Vec3::new(17.5, 65.0, 0.5))
+            .expect("Node should exist");
+        assert_eq!(IVec3::new(1, 4, 0), node.sub_chunk);
+        assert_eq!(Some(65.0), nav_world.node_height(node));
+
+        assert!(nav_world.node_at(Vec3::new(17.5, 65.0, 3.5)).is_none());
+        assert!(nav_world.node_at(Vec3::new(40.5, 65.0, 0.5)).is_none());
+    }
+
+    #[test]
+    fn nodes_in_region() {
+        let mut nav_world = NavWorld::default();
+        nav_world.insert(row(IVec3::ZERO));
+        nav_world.insert(row(IVec3::X));
+
+        let nodes = nav_world.nodes_in_region(Vec3::new(15.5, 0.0, 0.0), Vec3::new(16.5, 2.0, 1.0));
+        assert!(nodes.iter().any(|node| node.sub_chunk == IVec3::ZERO));
+        assert!(nodes.iter().any(|node| node.sub_chunk == IVec3::X));
+
+        assert!(nav_world
+            .nodes_in_region(Vec3::new(0.0, 2.0, 0.0), Vec3::new(32.0, 4.0, 1.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn path_across_sub_chunks() {
+        let mut nav_world = NavWorld::default();
+        nav_world.insert(row(IVec3::ZERO));
+        nav_world.insert(row(IVec3::X));
+
+        let path = find_path(
+            &nav_world,
+            Vec3::new(0.5, 1.0, 0.5),
+            Vec3::new(31.5, 1.0, 0.5),
+        )
+        .expect("Path should exist");
+        assert_eq!(IVec3::ZERO, path.nodes.first().unwrap().sub_chunk);
+        assert_eq!(IVec3::X, path.nodes.last().unwrap().sub_chunk);
+        assert!((path.cost - 31.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn step_up_across_sub_chunks() {
+        let mut nav_world = NavWorld::default();
+        nav_world.insert(row(IVec3::ZERO));
+
+        let mut source = empty_source();
+        for x in 0..CHUNK_WIDTH {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            source[0][x][1] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        }
+        nav_world.insert(SubChunk::new(IVec3::X, source));
+
+        let path = find_path(
+            &nav_world,
+            Vec3::new(0.5, 1.0, 0.5),
+            Vec3::new(31.5, 1.5, 0.5),
+        )
+        .expect("Path should exist");
+        assert_eq!(IVec3::X, path.nodes.last().unwrap().sub_chunk);
+        assert!(path
+            .portals
+            .iter()
+            .any(|portal| portal.link_type == Some(NavMeshLinkType::StepUp)));
+    }
+
+    #[test]
+    fn drop_to_sub_chunk_below() {
+        let mut nav_world = NavWorld::default();
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        nav_world.insert(SubChunk::new(IVec3::ZERO, source));
+
+        let mut source = empty_source();
+        source[0][1][CHUNK_WIDTH - 2] = smallvec![Aabb3D::FULL_BLOCK];
+        nav_world.insert(SubChunk::new(IVec3::NEG_Y, source));
+
+        let path = find_path(
+            &nav_world,
+            Vec3::new(0.5, 1.0, 0.5),
+            Vec3::new(1.5, -1.0, 0.5),
+        )
+        .expect("Path should exist");
+        assert_eq!(IVec3::NEG_Y, path.nodes.last().unwrap().sub_chunk);
+        assert_eq!(
+            Some(NavMeshLinkType::Drop),
+            path.portals.last().unwrap().link_type
+        );
+    }
+
+    #[test]
+    fn evict() {
+        let mut nav_world = NavWorld::default();
+        for x in -3..=3 {
+            nav_world.insert(row(IVec3::new(x, 0, 0)));
+        }
+        nav_world.mark_dirty(IVec3::new(3, 0, 0));
+
+        let mut evicted = nav_world.evict(&[IVec3::ZERO, IVec3::new(-2, 0, 0)], IVec3::ONE);
+        evicted.sort_by_key(|index| index.x);
+        assert_eq!(vec![IVec3::new(2, 0, 0), IVec3::new(3, 0, 0)], evicted);
+        assert_eq!(5, nav_world.len());
+        assert_eq!(0, nav_world.dirty_len());
+
+        assert_eq!(5, nav_world.evict(&[], IVec3::ONE).len());
+        assert!(nav_world.is_empty());
+    }
+
+    #[test]
+    fn no_path_to_missing_sub_chunk() {
+        let mut nav_world = NavWorld::default();
+        nav_world.insert(row(IVec3::ZERO));
+        nav_world.insert(row(IVec3::new(2, 0, 0)));
+
+        assert!(find_path(
+            &nav_world,
+            Vec3::new(0.5, 1.0, 0.5),
+            Vec3::new(32.5, 1.0, 0.5),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn mark_block_dirty() {
+        let mut nav_world = NavWorld::default();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                nav_world.insert(row(IVec3::new(x, y, 0)));
+            }
+        }
+
+        nav_world.mark_block_dirty(IVec3::new(4, 4, 4));
+        assert_eq!(1, nav_world.dirty_len());
+        assert!(nav_world.is_dirty(IVec3::ZERO));
+
+        nav_world.take_dirty(usize::MAX);
+        nav_world.mark_block_dirty(IVec3::new(15, 1, 4));
+        assert!(nav_world.is_dirty(IVec3::ZERO));
+        assert!(nav_world.is_dirty(IVec3::X));
+        assert!(nav_world.is_dirty(IVec3::NEG_Y));
+        assert!(nav_world.is_dirty(IVec3::new(1, -1, 0)));
+        assert!(!nav_world.is_dirty(IVec3::NEG_X));
+        assert_eq!(4, nav_world.dirty_len());
+    }
+
+    #[test]
+    fn insert_built_keeps_dirty() {
+        let mut nav_world = NavWorld::default();
+        nav_world.insert(row(IVec3::ZERO));
+
+        // Marked while a rebuild was in progress, the result may be out of date
+        let rebuilt = NavSubChunk::new(row(IVec3::ZERO), nav_world.agent());
+        nav_world.mark_dirty(IVec3::ZERO);
+        nav_world.insert_built(rebuilt);
+        assert!(nav_world.is_dirty(IVec3::ZERO));
+
+        nav_world.insert(row(IVec3::ZERO));
+        assert!(!nav_world.is_dirty(IVec3::ZERO));
+    }
+
+    #[test]
+    fn block_sub_chunks() {
+        let nav_world = NavWorld::default();
+        let inside: Vec<IVec3> = nav_world.block_sub_chunks(IVec3::new(4, 4, 4)).collect();
+        assert_eq!(vec![IVec3::ZERO], inside);
+
+        let corner: Vec<IVec3> = nav_world.block_sub_chunks(IVec3::new(0, 0, 0)).collect();
+        assert_eq!(8, corner.len());
+        assert!(corner.contains(&IVec3::new(-1, -1, -1)));
+    }
+
+    #[test]
+    fn mark_unbuilt_sub_chunk() {
+        let mut nav_world = NavWorld::default();
+        nav_world.mark_block_dirty(IVec3::new(4, 4, 4));
+        assert_eq!(0, nav_world.dirty_len());
+    }
+
+    #[test]
+    fn take_dirty_budget() {
+        let mut nav_world = NavWorld::default();
+        for x in 0..3 {
+            nav_world.insert(row(IVec3::new(x, 0, 0)));
+            nav_world.mark_dirty(IVec3::new(x, 0, 0));
+        }
+
+        assert_eq!(2, nav_world.take_dirty(2).len());
+        assert_eq!(1, nav_world.dirty_len());
+
+        let remaining = nav_world.take_dirty(2);
+        assert_eq!(1, remaining.len());
+        nav_world.mark_dirty(remaining[0]);
+        nav_world.insert(row(remaining[0]));
+        assert_eq!(0, nav_world.dirty_len());
+    }
+
+    #[test]
+    fn take_dirty_nearest() {
+        let mut nav_world = NavWorld::default();
+        for x in 0..4 {
+            nav_world.insert(row(IVec3::new(x, 0, 0)));
+            nav_world.mark_dirty(IVec3::new(x, 0, 0));
+        }
+
+        assert_eq!(
+            vec![IVec3::new(3, 0, 0), IVec3::new(2, 0, 0)],
+            nav_world.take_dirty_nearest(&[IVec3::new(4, 0, 0)], 2)
+        );
+        assert_eq!(
+            vec![IVec3::new(0, 0, 0), IVec3::new(1, 0, 0)],
+            nav_world.take_dirty_nearest(&[IVec3::new(0, 0, 0), IVec3::new(3, 0, 0)], 2)
+        );
+        assert_eq!(0, nav_world.dirty_len());
+    }
+}
@@ -0,0 +1,130 @@
+mod common;
+
+#[cfg(test)]
+mod pathfind {
+    use bevy::math::{IVec3, Vec3};
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{NavMeshLinkType, SubChunk},
+        pathfind::find_path,
+    };
+
+    use crate::common::empty_source;
+
+    #[test]
+    fn straight_row() {
+        let mut source = empty_source();
+        for x in 0..5 {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+
+        // The row is merged into a single node
+        let path = find_path(&nav, Vec3::new(0.5, 1.0, 0.5), Vec3::new(4.5, 1.0, 0.5))
+            .expect("Path should exist");
+        assert_eq!(1, path.nodes.len());
+        assert!(path.portals.is_empty());
+    }
+
+    #[test]
+    fn staggered_row() {
+        let mut source = empty_source();
+        for x in 0..5 {
+            source[x % 2][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+
+        let path = find_path(&nav, Vec3::new(0.5, 1.0, 0.5), Vec3::new(4.5, 1.0, 0.5))
+            .expect("Path should exist");
+        assert!(path.nodes.len() > 1);
+        assert_eq!(path.nodes.len() - 1, path.portals.len());
+        for portal in path.portals.iter() {
+            assert_eq!(1.0, portal.min.y);
+            assert!(portal.link_type.is_none());
+        }
+    }
+
+    #[test]
+    fn same_node() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+
+        let path = find_path(&nav, Vec3::new(0.2, 1.0, 0.2), Vec3::new(0.8, 1.0, 0.2))
+            .expect("Path should exist");
+        assert_eq!(1, path.nodes.len());
+        assert!(path.portals.is_empty());
+    }
+
+    #[test]
+    fn gap() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][4][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+
+        assert!(find_path(&nav, Vec3::new(0.5, 1.0, 0.5), Vec3::new(4.5, 1.0, 0.5)).is_none());
+    }
+
+    #[test]
+    fn around_wall() {
+        let mut source = empty_source();
+        for z in 0..3 {
+            for x in 0..3 {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        for z in 0..2 {
+            source[z][1][1] = smallvec![Aabb3D::FULL_BLOCK];
+            source[z][1][2] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+
+        let path = find_path(&nav, Vec3::new(0.5, 1.0, 0.5), Vec3::new(2.5, 1.0, 0.5))
+            .expect("Path should exist");
+        assert!(path.cost > 4.0);
+        assert!(path.portals.iter().any(|portal| portal.midpoint().z > 2.0));
+    }
+
+    #[test]
+    fn world_coordinates() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        source[0][2][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let nav = SubChunk::new(IVec3::new(1, 4, -1), source).build_nav_mesh(&AgentParams::PLAYER);
+
+        let path = find_path(
+            &nav,
+            Vec3::new(16.5, 65.0, -15.5),
+            Vec3::new(18.5, 65.0, -15.5),
+        )
+        .expect("Path should exist");
+        assert_eq!(2, path.nodes.len());
+        assert_eq!(65.0, path.portals[0].midpoint().y);
+    }
+
+    #[test]
+    fn step_up_and_drop() {
+        let mut source = empty_source();
+        for x in 0..5 {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        source[0][2][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let nav = SubChunk::new(IVec3::ZERO, source).build_nav_mesh(&AgentParams::PLAYER);
+
+        let path = find_path(&nav, Vec3::new(0.5, 1.0, 0.5), Vec3::new(4.5, 1.0, 0.5))
+            .expect("Path should exist");
+        let link_types: Vec<_> = path
+            .portals
+            .iter()
+            .flat_map(|portal| portal.link_type)
+            .collect();
+        assert_eq!(
+            vec![NavMeshLinkType::JumpUp, NavMeshLinkType::Drop],
+            link_types
+        );
+        assert!(path.cost > 4.0);
+    }
+}
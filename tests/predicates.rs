@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod aabb_predicates {
+    use wallace::aabb::{aabb_2d::Aabb2D, aabb_3d::Aabb3D, predicates::*};
+
+    fn square(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Aabb2D {
+        Aabb2D {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    #[test]
+    fn overlaps_2d() {
+        let a = square(0.0, 0.0, 1.0, 1.0);
+        assert!(overlaps(&a, &square(0.5, 0.5, 1.5, 1.5), EPSILON));
+        assert!(!overlaps(&a, &square(1.0, 0.0, 2.0, 1.0), EPSILON));
+        assert!(!overlaps(&a, &square(1.0, 1.0, 2.0, 2.0), EPSILON));
+        assert!(!overlaps(&a, &square(3.0, 3.0, 4.0, 4.0), EPSILON));
+        assert!(!overlaps(&a, &square(0.0, 3.0, 1.0, 4.0), EPSILON));
+    }
+
+    #[test]
+    fn aabb_2d_overlaps() {
+        let a = square(0.0, 0.0, 1.0, 1.0);
+        assert!(a.overlaps(&square(-0.5, 0.5, 0.5, 1.5)));
+        assert!(!a.overlaps(&square(2.0, 2.0, 3.0, 3.0)));
+        assert!(!a.overlaps(&square(-2.0, -2.0, -1.0, -1.0)));
+        assert!(!a.overlaps(&square(1.0, 0.0, 2.0, 1.0)));
+    }
+
+    #[test]
+    fn touching_2d() {
+        let a = square(0.0, 0.0, 1.0, 1.0);
+        let edge = square(1.0, 0.5, 2.0, 1.5);
+        let corner = square(1.0, 1.0, 2.0, 2.0);
+
+        assert!(touching_edge(&a, &edge, EPSILON));
+        assert!(!touching_corner(&a, &edge, EPSILON));
+        assert!(touching_corner(&a, &corner, EPSILON));
+        assert!(!touching_edge(&a, &corner, EPSILON));
+        assert!(intersects(&a, &corner, EPSILON));
+        assert!(!intersects(&a, &square(1.5, 0.0, 2.0, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn epsilon() {
+        let a = square(0.0, 0.0, 1.0, 1.0);
+        let b = square(1.001, 0.0, 2.0, 1.0);
+        assert!(!touching_edge(&a, &b, EPSILON));
+        assert!(touching_edge(&a, &b, 0.01));
+
+        let c = square(0.999, 0.0, 2.0, 1.0);
+        assert!(overlaps(&a, &c, EPSILON));
+        assert!(!overlaps(&a, &c, 0.01));
+    }
+
+    #[test]
+    fn contains_2d() {
+        let a = square(0.0, 0.0, 1.0, 1.0);
+        assert!(contains(&a, &square(0.25, 0.25, 0.75, 0.75), EPSILON));
+        assert!(contains(&a, &a, EPSILON));
+        assert!(!contains(&a, &square(0.25, 0.25, 1.25, 0.75), EPSILON));
+        assert!(contains(&a, &square(0.0, 0.0, 1.00001, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn predicates_3d() {
+        let a = Aabb3D::FULL_BLOCK;
+        let face = Aabb3D([0.0, 1.0, 0.0, 1.0, 2.0, 1.0]);
+        let edge = Aabb3D([1.0, 1.0, 0.0, 2.0, 2.0, 1.0]);
+        let corner = Aabb3D([1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+        let inner = Aabb3D([0.25, 0.0, 0.25, 0.75, 0.5, 0.75]);
+
+        assert!(touching_edge(&a, &face, EPSILON));
+        assert!(!overlaps(&a, &face, EPSILON));
+        assert!(touching_corner(&a, &edge, EPSILON));
+        assert!(touching_corner(&a, &corner, EPSILON));
+        assert!(overlaps(&a, &inner, EPSILON));
+        assert!(contains(&a, &inner, EPSILON));
+        assert!(!contains(&inner, &a, EPSILON));
+        assert!(a.overlaps(&inner));
+        assert!(!a.overlaps(&corner));
+    }
+}
@@ -0,0 +1,153 @@
+mod common;
+
+#[cfg(test)]
+mod serialise {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{NavNodeId, SubChunk, SubChunkHalo, SubChunkNavMesh},
+        serialise::{SubChunkData, FORMAT_VERSION},
+    };
+
+    use crate::common::empty_source;
+
+    fn sub_chunk() -> SubChunk {
+        let mut source = empty_source();
+        for z in 0..4 {
+            for x in 0..4 {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        source[1][1][1] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        source[2][2][1] = smallvec![Aabb3D([0.375, 0.0, 0.375, 0.625, 1.5, 0.625])];
+        source[3][0][1] = smallvec![
+            Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0]),
+            Aabb3D([0.0, 0.5, 0.5, 1.0, 1.0, 1.0])
+        ];
+
+        let mut halo = SubChunkHalo::default();
+        halo.insert(IVec3::new(-1, 0, 0), smallvec![Aabb3D::FULL_BLOCK]);
+        halo.insert(IVec3::new(2, 16, 2), smallvec![Aabb3D::FULL_BLOCK]);
+        SubChunk::new_with_halo(IVec3::new(-2, 3, 5), source, halo)
+    }
+
+    fn nav_mesh_bytes(nav: &SubChunkNavMesh) -> Vec<u8> {
+        let mut bytes = vec![];
+        nav.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn sub_chunk_round_trip() {
+        let sub_chunk = sub_chunk();
+        let mut bytes = vec![];
+        sub_chunk.write_to(&mut bytes).unwrap();
+
+        let loaded = SubChunk::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(sub_chunk.location, loaded.location);
+        assert_eq!(SubChunkData::new(&sub_chunk), SubChunkData::new(&loaded));
+        assert_eq!(
+            sub_chunk.iter_collisions().collect::<Vec<_>>(),
+            loaded.iter_collisions().collect::<Vec<_>>()
+        );
+
+        // The rebuilt sub chunk produces the same nav mesh
+        assert_eq!(
+            nav_mesh_bytes(&sub_chunk.build_nav_mesh(&AgentParams::PLAYER)),
+            nav_mesh_bytes(&loaded.build_nav_mesh(&AgentParams::PLAYER))
+        );
+    }
+
+    #[test]
+    fn nav_mesh_round_trip() {
+        let nav = sub_chunk().build_nav_mesh(&AgentParams::PLAYER);
+        let bytes = nav_mesh_bytes(&nav);
+
+        let loaded = SubChunkNavMesh::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(nav.location, loaded.location);
+        assert_eq!(nav.agent, loaded.agent);
+        assert_eq!(nav.floor.len(), loaded.floor.len());
+        for (layer, loaded_layer) in nav.floor.iter().zip(loaded.floor.iter()) {
+            assert_eq!(layer.height, loaded_layer.height);
+            assert_eq!(layer.nodes.len(), loaded_layer.nodes.len());
+        }
+        assert_eq!(bytes, nav_mesh_bytes(&loaded));
+    }
+
+    #[test]
+    fn header() {
+        let mut bytes = vec![];
+        sub_chunk().write_to(&mut bytes).unwrap();
+        assert_eq!(b"WSUB", &bytes[0..4]);
+        assert_eq!(FORMAT_VERSION.to_le_bytes(), bytes[4..8]);
+
+        // Nav meshes and sub chunks can't be mixed up
+        assert!(SubChunkNavMesh::read_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut bytes = vec![];
+        sub_chunk().write_to(&mut bytes).unwrap();
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(SubChunk::read_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut bytes = vec![];
+        sub_chunk().write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() / 2);
+        assert!(SubChunk::read_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn corrupt_length() {
+        let sub_chunk = sub_chunk();
+        let mut bytes = vec![];
+        sub_chunk.write_to(&mut bytes).unwrap();
+
+        // Length of the blocks, after the header and location
+        let blocks = SubChunkData::new(&sub_chunk).blocks.len() as u64;
+        assert_eq!(blocks.to_le_bytes(), bytes[20..28]);
+        bytes[20..28].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(SubChunk::read_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn invalid_link_target() {
+        let mut nav = sub_chunk().build_nav_mesh(&AgentParams::PLAYER);
+        let node = nav
+            .floor
+            .iter_mut()
+            .flat_map(|layer| layer.nodes.iter_mut())
+            .find(|node| !node.links.is_empty())
+            .expect("Slabs should have links");
+        node.links[0].to = NavNodeId {
+            layer: 100,
+            node: 0,
+        };
+        assert!(SubChunkNavMesh::read_from(nav_mesh_bytes(&nav).as_slice()).is_err());
+    }
+
+    #[test]
+    fn invalid_block_index() {
+        let mut nav = sub_chunk().build_nav_mesh(&AgentParams::PLAYER);
+        nav.ceiling[0].blocks[0][0].push(1000);
+        assert!(SubChunkNavMesh::read_from(nav_mesh_bytes(&nav).as_slice()).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let sub_chunk = sub_chunk();
+        let loaded = SubChunk::from_json(&sub_chunk.to_json().unwrap()).unwrap();
+        assert_eq!(SubChunkData::new(&sub_chunk), SubChunkData::new(&loaded));
+
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER);
+        let loaded = SubChunkNavMesh::from_json(&nav.to_json().unwrap()).unwrap();
+        assert_eq!(nav_mesh_bytes(&nav), nav_mesh_bytes(&loaded));
+    }
+}
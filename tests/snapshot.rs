@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod world_snapshot {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D, agent::AgentParams, serialise::SubChunkData, snapshot::WorldSnapshot,
+    };
+
+    fn snapshot() -> WorldSnapshot {
+        let mut snapshot = WorldSnapshot::new(IVec3::new(-8, 60, -8), IVec3::new(8, 70, 8));
+        for z in -8..8 {
+            for x in -8..8 {
+                snapshot.insert(IVec3::new(x, 63, z), smallvec![Aabb3D::FULL_BLOCK]);
+            }
+        }
+        snapshot.insert(
+            IVec3::new(0, 64, 0),
+            smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])],
+        );
+        snapshot
+    }
+
+    #[test]
+    fn insert() {
+        let mut snapshot = snapshot();
+        assert_eq!(257, snapshot.len());
+        assert_eq!(&[Aabb3D::FULL_BLOCK], snapshot.get(IVec3::new(3, 63, -2)));
+        assert!(snapshot.get(IVec3::new(3, 64, -2)).is_empty());
+
+        // Outside of the captured region
+        snapshot.insert(IVec3::new(8, 63, 0), smallvec![Aabb3D::FULL_BLOCK]);
+        assert!(snapshot.get(IVec3::new(8, 63, 0)).is_empty());
+
+        snapshot.insert(IVec3::new(0, 64, 0), smallvec![]);
+        assert_eq!(256, snapshot.len());
+    }
+
+    #[test]
+    fn sub_chunk_indices() {
+        let mut indices: Vec<IVec3> = snapshot().sub_chunk_indices().collect();
+        indices.sort_by_key(|index| (index.x, index.y, index.z));
+        assert_eq!(
+            vec![
+                IVec3::new(-1, 3, -1),
+                IVec3::new(-1, 3, 0),
+                IVec3::new(-1, 4, -1),
+                IVec3::new(-1, 4, 0),
+                IVec3::new(0, 3, -1),
+                IVec3::new(0, 3, 0),
+                IVec3::new(0, 4, -1),
+                IVec3::new(0, 4, 0),
+            ],
+            indices
+        );
+    }
+
+    #[test]
+    fn source() {
+        let snapshot = snapshot();
+        let source = snapshot.source(IVec3::new(0, 3, 0));
+        assert_eq!(vec![Aabb3D::FULL_BLOCK], source[0][0][15].to_vec());
+        assert_eq!(vec![Aabb3D::FULL_BLOCK], source[7][7][15].to_vec());
+        assert!(source[8][8][15].is_empty());
+        assert!(source[0][0][14].is_empty());
+
+        // The slab above is in the halo of the sub chunk below it
+        let sub_chunk = snapshot.sub_chunk(IVec3::new(0, 3, 0), &AgentParams::PLAYER);
+        let data = SubChunkData::new(&sub_chunk);
+        assert!(data.halo.contains(&(
+            IVec3::new(0, 16, 0),
+            vec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])]
+        )));
+        assert!(data
+            .halo
+            .contains(&(IVec3::new(-1, 15, 0), vec![Aabb3D::FULL_BLOCK])));
+    }
+
+    #[test]
+    fn around() {
+        let snapshot = WorldSnapshot::around(IVec3::new(8, 64, 8), 4, &AgentParams::PLAYER);
+        assert_eq!(IVec3::new(-1, 47, -1), snapshot.min);
+        assert_eq!(IVec3::new(17, 82, 17), snapshot.max);
+        assert!(snapshot.covers(IVec3::new(0, 3, 0), &AgentParams::PLAYER));
+        assert!(snapshot.covers(IVec3::new(0, 4, 0), &AgentParams::PLAYER));
+        assert!(!snapshot.covers(IVec3::new(0, 5, 0), &AgentParams::PLAYER));
+        assert!(!snapshot.covers(IVec3::new(1, 4, 0), &AgentParams::PLAYER));
+    }
+
+    #[test]
+    fn partly_covered_sub_chunk() {
+        let snapshot = snapshot();
+        assert!(snapshot
+            .sub_chunk_indices()
+            .all(|index| !snapshot.covers(index, &AgentParams::PLAYER)));
+    }
+
+    #[test]
+    fn nav_mesh() {
+        let snapshot = snapshot();
+        let nav = snapshot
+            .sub_chunk(IVec3::new(0, 3, 0), &AgentParams::PLAYER)
+            .build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav.floor.iter().any(|layer| layer.height == 16.0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let snapshot = snapshot();
+        let mut bytes = vec![];
+        snapshot.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            snapshot,
+            WorldSnapshot::read_from(bytes.as_slice()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let snapshot = snapshot();
+        let json = snapshot.to_json().unwrap();
+        assert_eq!(snapshot, WorldSnapshot::from_json(&json).unwrap());
+    }
+}
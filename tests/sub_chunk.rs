@@ -0,0 +1,286 @@
+mod common;
+
+#[cfg(test)]
+mod sub_chunk_halo {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_2d::Point2D,
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{SubChunk, SubChunkHalo},
+    };
+
+    use crate::common::empty_source;
+
+    #[test]
+    fn halo_positions() {
+        let halo = SubChunkHalo::default();
+        assert!(halo.contains(IVec3::new(-1, 0, 0)));
+        assert!(halo.contains(IVec3::new(0, 17, 0)));
+        assert!(!halo.contains(IVec3::new(0, 18, 0)));
+        assert!(!halo.contains(IVec3::new(0, 0, 0)));
+        assert!(halo.positions().all(|pos| !SubChunk::contains(pos)));
+        assert_eq!(halo.positions().count(), 18 * 19 * 18 - 16 * 16 * 16);
+    }
+
+    #[test]
+    fn halo_sized_for_agent() {
+        let agent = AgentParams {
+            radius: 0.7,
+            height: 2.5,
+            ..AgentParams::PLAYER
+        };
+        let halo = SubChunkHalo::new(&agent);
+        assert_eq!(IVec3::new(-2, -1, -2), halo.min());
+        assert_eq!(IVec3::new(17, 18, 17), halo.max());
+        assert!(halo.contains(IVec3::new(17, 18, 17)));
+    }
+
+    #[test]
+    fn floor_occluded_by_halo_above() {
+        let mut source = empty_source();
+        source[0][0][15] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source.clone()).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(1, nav.floor.len());
+
+        let mut halo = SubChunkHalo::default();
+        halo.insert(IVec3::new(0, 16, 0), smallvec![Aabb3D::FULL_BLOCK]);
+        let nav =
+            SubChunk::new_with_halo(IVec3::ZERO, source, halo).build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(0, nav.floor.len());
+    }
+
+    #[test]
+    fn floor_cut_by_halo_side() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let nav = SubChunk::new(IVec3::ZERO, source.clone()).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav.floor[0].nodes.iter().any(|node| node.aabb.min_x < 0.0));
+
+        let mut halo = SubChunkHalo::default();
+        halo.insert(IVec3::new(-1, 1, 0), smallvec![Aabb3D::FULL_BLOCK]);
+        let nav =
+            SubChunk::new_with_halo(IVec3::ZERO, source, halo).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(!nav.floor[0].nodes.is_empty());
+        assert!(nav.floor[0].nodes.iter().all(|node| node.aabb.min_x >= 0.3));
+    }
+
+    #[test]
+    fn floor_cut_by_tall_halo_below() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.0625, 1.0])];
+
+        let mut halo = SubChunkHalo::default();
+        halo.insert(
+            IVec3::new(-1, -1, 0),
+            smallvec![Aabb3D([0.375, 0.0, 0.375, 0.625, 1.5, 0.625])],
+        );
+        let beside_fence = Point2D { x: -0.2, y: 0.5 };
+
+        let nav = SubChunk::new(IVec3::ZERO, source.clone()).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav.floor[0]
+            .nodes
+            .iter()
+            .any(|node| node.aabb.contains(&beside_fence)));
+
+        let nav =
+            SubChunk::new_with_halo(IVec3::ZERO, source, halo).build_nav_mesh(&AgentParams::PLAYER);
+        assert!(!nav.floor[0]
+            .nodes
+            .iter()
+            .any(|node| node.aabb.contains(&beside_fence)));
+    }
+
+    #[test]
+    fn floor_occluded_by_other_above() {
+        let mut source = empty_source();
+        source[0][0][15] = smallvec![Aabb3D::FULL_BLOCK];
+        let mut sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let above = SubChunk::new(IVec3::Y, source);
+
+        assert_eq!(
+            1,
+            sub_chunk.build_nav_mesh(&AgentParams::PLAYER).floor.len()
+        );
+        sub_chunk.apply_other_occlusion(&above);
+        assert_eq!(
+            0,
+            sub_chunk.build_nav_mesh(&AgentParams::PLAYER).floor.len()
+        );
+    }
+
+    #[test]
+    fn floor_cut_by_other_beside() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let mut sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let mut source = empty_source();
+        source[0][15][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let beside = SubChunk::new(IVec3::NEG_X, source);
+
+        sub_chunk.apply_other_occlusion(&beside);
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER);
+        assert!(nav.floor[0].nodes.iter().all(|node| node.aabb.min_x >= 0.3));
+    }
+
+    #[test]
+    fn other_occlusion_ignores_distant() {
+        let mut source = empty_source();
+        source[0][0][15] = smallvec![Aabb3D::FULL_BLOCK];
+        let mut sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        let distant = SubChunk::new(IVec3::new(0, 2, 0), source);
+
+        sub_chunk.apply_other_occlusion(&distant);
+        assert_eq!(
+            1,
+            sub_chunk.build_nav_mesh(&AgentParams::PLAYER).floor.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod sub_chunk_greedy_meshing {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        optimise_world::{SubChunk, CHUNK_WIDTH},
+    };
+
+    use crate::common::empty_source;
+
+    fn total_volume(sub_chunk: &SubChunk) -> f32 {
+        sub_chunk
+            .iter_collisions()
+            .map(|(_, aabb)| aabb.volume())
+            .sum()
+    }
+
+    #[test]
+    fn full_layer() {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                source[z][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+            }
+        }
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let collisions: Vec<_> = sub_chunk.iter_collisions().collect();
+        assert_eq!(1, collisions.len());
+        assert_eq!(&Aabb3D([0.0, 0.0, 0.0, 16.0, 1.0, 16.0]), collisions[0].1);
+    }
+
+    #[test]
+    fn full_sub_chunk() {
+        let mut source = empty_source();
+        for plane in source.iter_mut() {
+            for column in plane.iter_mut() {
+                for block in column.iter_mut() {
+                    *block = smallvec![Aabb3D::FULL_BLOCK];
+                }
+            }
+        }
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+        assert_eq!(1, sub_chunk.iter_collisions().count());
+        assert_eq!(4096.0, total_volume(&sub_chunk));
+    }
+
+    #[test]
+    fn mixed_blocks() {
+        let mut source = empty_source();
+        // L shape of full blocks with a slab in the corner
+        for x in 0..4 {
+            source[0][x][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        for z in 1..4 {
+            source[z][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        source[1][1][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        assert_eq!(3, sub_chunk.iter_collisions().count());
+        assert_eq!(7.5, total_volume(&sub_chunk));
+    }
+
+    #[test]
+    fn merged_boxes_do_not_overlap() {
+        let mut source = empty_source();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                for y in 0..(x + z) % 5 {
+                    source[z][x][y] = smallvec![Aabb3D::FULL_BLOCK];
+                }
+            }
+        }
+        let expected: usize = (0..CHUNK_WIDTH)
+            .flat_map(|z| (0..CHUNK_WIDTH).map(move |x| (x + z) % 5))
+            .sum();
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        assert_eq!(expected as f32, total_volume(&sub_chunk));
+        assert!(sub_chunk.iter_collisions().count() < expected);
+    }
+}
+
+#[cfg(test)]
+mod sub_chunk_ceiling {
+    use bevy::math::IVec3;
+    use smallvec::smallvec;
+    use wallace::aabb::{
+        aabb_3d::Aabb3D,
+        agent::AgentParams,
+        optimise_world::{SubChunk, SubChunkHalo},
+    };
+
+    use crate::common::empty_source;
+
+    #[test]
+    fn buried_ceilings() {
+        let mut source = empty_source();
+        for y in 0..4 {
+            source[0][0][y] = smallvec![Aabb3D::FULL_BLOCK];
+        }
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        let ceiling: Vec<_> = sub_chunk.iter_ceiling().collect();
+        assert_eq!(1, ceiling.len());
+        assert_eq!(0, ceiling[0].0.y);
+
+        let nav = sub_chunk.build_nav_mesh(&AgentParams::PLAYER);
+        assert_eq!(1, nav.ceiling.len());
+        assert_eq!(0.0, nav.ceiling[0].height);
+    }
+
+    #[test]
+    fn ceiling_above_slab() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D([0.0, 0.0, 0.0, 1.0, 0.5, 1.0])];
+        source[0][0][1] = smallvec![Aabb3D::FULL_BLOCK];
+        let sub_chunk = SubChunk::new(IVec3::ZERO, source);
+
+        assert_eq!(2, sub_chunk.iter_ceiling().count());
+    }
+
+    #[test]
+    fn ceiling_occluded_by_halo_below() {
+        let mut source = empty_source();
+        source[0][0][0] = smallvec![Aabb3D::FULL_BLOCK];
+
+        let mut halo = SubChunkHalo::default();
+        halo.insert(IVec3::new(0, -1, 0), smallvec![Aabb3D::FULL_BLOCK]);
+        let sub_chunk = SubChunk::new_with_halo(IVec3::ZERO, source, halo);
+
+        assert_eq!(0, sub_chunk.iter_ceiling().count());
+    }
+}